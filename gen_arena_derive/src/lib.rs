@@ -0,0 +1,188 @@
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// Derives `GenIndex` for a tuple struct wrapping an `Id`.
+///
+/// ```ignore
+/// #[derive(GenIndex)]
+/// #[gen_index(item = Unit)]
+/// struct UnitId(Id);
+/// ```
+///
+/// Besides `GenIndex`, this generates `From` conversions in both directions as
+/// well as `Clone`, `Copy`, `PartialEq`, `Eq`, `Hash`, `Debug` and `Display`,
+/// so none of those should be derived separately.
+#[proc_macro_derive(GenIndex, attributes(gen_index))]
+pub fn derive_gen_index(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(output) => output,
+        Err(message) => format!("::core::compile_error!({message:?});")
+            .parse()
+            .unwrap(),
+    }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, String> {
+    let mut tokens = input.into_iter().peekable();
+    let mut item = None;
+
+    while let Some(TokenTree::Punct(punct)) = tokens.peek() {
+        if punct.as_char() != '#' {
+            break;
+        }
+        tokens.next();
+        let Some(TokenTree::Group(attr)) = tokens.next() else {
+            return Err("expected an attribute".into());
+        };
+        if let Some(found) = parse_gen_index_attr(attr.stream())? {
+            item = Some(found);
+        }
+    }
+
+    skip_visibility(&mut tokens);
+    match tokens.next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "struct" => (),
+        _ => return Err("GenIndex can only be derived for structs".into()),
+    }
+    let Some(TokenTree::Ident(name)) = tokens.next() else {
+        return Err("expected a struct name".into());
+    };
+    let field = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+            parse_field(group.stream())?
+        }
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+            return Err("GenIndex cannot be derived for generic structs".into());
+        }
+        _ => return Err("GenIndex can only be derived for tuple structs".into()),
+    };
+    let Some(item) = item else {
+        return Err("missing `#[gen_index(item = ...)]` attribute".into());
+    };
+
+    Ok(generate(&name.to_string(), &field, &item)
+        .parse()
+        .expect("generated code is valid"))
+}
+
+/// Returns the item type if `attr` is `gen_index(item = ...)`.
+fn parse_gen_index_attr(attr: TokenStream) -> Result<Option<String>, String> {
+    let mut tokens = attr.into_iter();
+    match tokens.next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "gen_index" => (),
+        _ => return Ok(None),
+    }
+    let Some(TokenTree::Group(args)) = tokens.next() else {
+        return Err("expected `#[gen_index(item = ...)]`".into());
+    };
+
+    let mut args = args.stream().into_iter();
+    match (args.next(), args.next()) {
+        (Some(TokenTree::Ident(key)), Some(TokenTree::Punct(eq)))
+            if key.to_string() == "item" && eq.as_char() == '=' => {}
+        _ => return Err("expected `#[gen_index(item = ...)]`".into()),
+    }
+    let item = take_type(args);
+    if item.is_empty() {
+        return Err("expected a type after `item =`".into());
+    }
+    Ok(Some(item.to_string()))
+}
+
+/// Returns the type of the single field of a tuple struct.
+fn parse_field(field: TokenStream) -> Result<String, String> {
+    let mut tokens = field.into_iter().peekable();
+    skip_visibility(&mut tokens);
+
+    let ty = take_type(tokens);
+    if ty.is_empty() {
+        return Err("GenIndex structs must wrap exactly one `Id`".into());
+    }
+    Ok(ty.to_string())
+}
+
+/// Collects the tokens of a type, up to the first comma outside of angle brackets.
+fn take_type(tokens: impl Iterator<Item = TokenTree>) -> TokenStream {
+    let mut ty = Vec::new();
+    let mut depth = 0;
+    let mut after_minus = false;
+    for token in tokens {
+        if let TokenTree::Punct(punct) = &token {
+            match punct.as_char() {
+                '<' => depth += 1,
+                // The `>` of `->` does not close an angle bracket.
+                '>' if !after_minus => depth -= 1,
+                ',' if depth == 0 => break,
+                _ => (),
+            }
+            after_minus = punct.as_char() == '-' && punct.spacing() == Spacing::Joint;
+        } else {
+            after_minus = false;
+        }
+        ty.push(token);
+    }
+    ty.into_iter().collect()
+}
+
+fn skip_visibility(tokens: &mut std::iter::Peekable<impl Iterator<Item = TokenTree>>) {
+    if let Some(TokenTree::Ident(ident)) = tokens.peek() {
+        if ident.to_string() == "pub" {
+            tokens.next();
+            if let Some(TokenTree::Group(group)) = tokens.peek() {
+                if group.delimiter() == Delimiter::Parenthesis {
+                    tokens.next();
+                }
+            }
+        }
+    }
+}
+
+fn generate(name: &str, field: &str, item: &str) -> String {
+    format!(
+        r#"
+        impl ::core::convert::From<{field}> for {name} {{
+            fn from(id: {field}) -> Self {{
+                Self(id)
+            }}
+        }}
+        impl ::core::convert::From<{name}> for {field} {{
+            fn from(id: {name}) -> Self {{
+                id.0
+            }}
+        }}
+        impl ::gen_arena::GenIndex for {name} {{
+            type Item = {item};
+            type Layout = <{field} as ::gen_arena::RawId>::Layout;
+            fn get_id(&self) -> {field} {{
+                self.0
+            }}
+        }}
+        impl ::core::clone::Clone for {name} {{
+            fn clone(&self) -> Self {{
+                *self
+            }}
+        }}
+        impl ::core::marker::Copy for {name} {{}}
+        impl ::core::cmp::PartialEq for {name} {{
+            fn eq(&self, other: &Self) -> bool {{
+                self.0 == other.0
+            }}
+        }}
+        impl ::core::cmp::Eq for {name} {{}}
+        impl ::core::hash::Hash for {name} {{
+            fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {{
+                ::core::hash::Hash::hash(&self.0, state)
+            }}
+        }}
+        impl ::core::fmt::Debug for {name} {{
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {{
+                f.debug_tuple("{name}").field(&self.0).finish()
+            }}
+        }}
+        impl ::core::fmt::Display for {name} {{
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {{
+                ::core::write!(f, "{name}({{}}, {{}})", self.0.index(), self.0.generation())
+            }}
+        }}
+        "#
+    )
+}
@@ -0,0 +1,466 @@
+use crate::{
+    layout, ArenaTag, CapacityError, Entry, Id, InsertError, Integer, Iter, IterMut,
+    IterMutWithIds, IterWithIds, Key, KeyLayout, LookupError, OverflowPolicy, Slot, U32U32,
+};
+use std::{
+    fmt::Debug,
+    iter::FusedIterator,
+    ops::{Index, IndexMut},
+};
+
+/// An arena with room for exactly `N` values, stored inline.
+///
+/// It never allocates, so it can live on the stack or inside another value that
+/// must not touch the heap. Once every slot is taken, [`ArrayArena::insert`]
+/// hands the value back instead of growing.
+pub struct ArrayArena<T, const N: usize, K: Key<T> = Id> {
+    slots: [Slot<T, K::Layout>; N],
+    first_free: <K::Layout as KeyLayout>::Index,
+    free_count: usize,
+    retired_count: usize,
+    overflow_policy: OverflowPolicy,
+    tag: ArenaTag,
+}
+impl<T, const N: usize, K: Key<T>> ArrayArena<T, N, K> {
+    /// # Panics
+    /// Panics if `N` slots do not fit in the index space of the key layout.
+    pub fn new() -> Self {
+        assert!(
+            N == 0 || N - 1 <= K::Layout::MAX_INDEX.to_usize(),
+            "ArrayArena cannot hold {N} values with this key layout"
+        );
+        Self {
+            slots: std::array::from_fn(|index| Slot {
+                generation: Integer::ZERO,
+                entry: Entry::Free {
+                    next_free: if index + 1 < N {
+                        Integer::from_usize(index + 1)
+                    } else {
+                        Integer::ZERO
+                    },
+                },
+            }),
+            first_free: Integer::ZERO,
+            free_count: N,
+            retired_count: 0,
+            overflow_policy: OverflowPolicy::default(),
+            tag: ArenaTag::new(),
+        }
+    }
+
+    /// Removes every value while invalidating every outstanding key.
+    pub fn clear(&mut self) {
+        for index in 0..N {
+            if let Entry::Present(_) = self.slots[index].entry {
+                self.vacate(index);
+            }
+        }
+    }
+
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow_policy
+    }
+    /// Decides what happens to slots whose generation runs out from now on.
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.overflow_policy = policy;
+    }
+    /// The number of slots that have been retired because their generation ran out.
+    pub fn retired_count(&self) -> usize {
+        self.retired_count
+    }
+
+    /// The number of values the arena can hold, not counting retired slots.
+    pub fn capacity(&self) -> usize {
+        N - self.retired_count
+    }
+    pub fn len(&self) -> usize {
+        N - self.free_count - self.retired_count
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn is_full(&self) -> bool {
+        self.free_count == 0
+    }
+
+    /// Inserts `t`, or hands it back if every slot is taken.
+    pub fn insert(&mut self, t: T) -> Result<K, InsertError<T>> {
+        if self.free_count == 0 {
+            return Err(InsertError {
+                kind: CapacityError::MaxLenReached,
+                value: t,
+            });
+        }
+        let index = self.first_free.to_usize();
+        let slot = &mut self.slots[index];
+        let Entry::Free { next_free } = slot.entry else {
+            unreachable!()
+        };
+        self.first_free = next_free;
+        self.free_count -= 1;
+
+        slot.entry = Entry::Present(t);
+        Ok(K::from_id(Id::new(index, slot.generation, self.tag)))
+    }
+    pub fn remove(&mut self, key: K) -> Option<T> {
+        self.try_remove(key).ok()
+    }
+    pub fn try_remove(&mut self, key: K) -> Result<T, LookupError> {
+        let id = key.to_id();
+        self.lookup(id)?;
+        Ok(self.vacate(id.slot()))
+    }
+    pub fn contains(&self, key: K) -> bool {
+        self.lookup(key.to_id()).is_ok()
+    }
+
+    pub fn get(&self, key: K) -> Option<&T> {
+        self.try_get(key).ok()
+    }
+    pub fn get_mut(&mut self, key: K) -> Option<&mut T> {
+        self.try_get_mut(key).ok()
+    }
+    pub fn try_get(&self, key: K) -> Result<&T, LookupError> {
+        let slot = self.lookup(key.to_id())?;
+        let Entry::Present(item) = &slot.entry else {
+            unreachable!()
+        };
+        Ok(item)
+    }
+    pub fn try_get_mut(&mut self, key: K) -> Result<&mut T, LookupError> {
+        let id = key.to_id();
+        self.lookup(id)?;
+        let Entry::Present(item) = &mut self.slots[id.slot()].entry else {
+            unreachable!()
+        };
+        Ok(item)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T, K::Layout> {
+        Iter {
+            length: self.len(),
+            returned: 0,
+            slots: self.slots.iter(),
+        }
+    }
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, K::Layout> {
+        IterMut {
+            length: self.len(),
+            returned: 0,
+            slots: self.slots.iter_mut(),
+        }
+    }
+    pub fn iter_with_ids(&self) -> IterWithIds<'_, T, K> {
+        IterWithIds {
+            length: self.len(),
+            returned: 0,
+            tag: self.tag,
+            slots: self.slots.iter().enumerate(),
+        }
+    }
+    pub fn iter_mut_with_ids(&mut self) -> IterMutWithIds<'_, T, K> {
+        IterMutWithIds {
+            length: self.len(),
+            returned: 0,
+            tag: self.tag,
+            slots: self.slots.iter_mut().enumerate(),
+        }
+    }
+    pub fn into_iter_with_ids(self) -> IntoIterWithIds<T, N, K> {
+        IntoIterWithIds {
+            length: self.len(),
+            returned: 0,
+            tag: self.tag,
+            slots: self.slots.into_iter().enumerate(),
+        }
+    }
+
+    /// Removes every value, yielding it together with its former key.
+    ///
+    /// If the iterator is dropped early, the remaining values are removed anyway.
+    pub fn drain(&mut self) -> Drain<'_, T, N, K> {
+        Drain {
+            length: self.len(),
+            returned: 0,
+            index: 0,
+            arena: self,
+        }
+    }
+    /// Lazily removes and yields the values for which `f` returns `true`.
+    ///
+    /// If the iterator is dropped early, the values not yet visited are kept.
+    pub fn extract_if<F: FnMut(K, &mut T) -> bool>(&mut self, f: F) -> ExtractIf<'_, T, N, F, K> {
+        ExtractIf {
+            arena: self,
+            index: 0,
+            pred: f,
+        }
+    }
+
+    /// Keeps only the values for which `f` returns `true`.
+    pub fn retain<F: FnMut(K, &mut T) -> bool>(&mut self, mut f: F) {
+        for index in 0..N {
+            let slot = &mut self.slots[index];
+            let key = K::from_id(Id::new(index, slot.generation, self.tag));
+            if let Entry::Present(item) = &mut slot.entry {
+                if !f(key, item) {
+                    self.vacate(index);
+                }
+            }
+        }
+    }
+
+    fn vacate(&mut self, index: usize) -> T {
+        let slot = &mut self.slots[index];
+        if self.overflow_policy.retires::<K::Layout>(slot.generation) {
+            let Entry::Present(old) = std::mem::replace(&mut slot.entry, Entry::Retired) else {
+                unreachable!()
+            };
+            self.retired_count += 1;
+            return old;
+        }
+        slot.generation = layout::next_generation::<K::Layout>(slot.generation);
+
+        let Some(old) = slot.entry.take(self.first_free) else {
+            unreachable!()
+        };
+        self.first_free = Integer::from_usize(index);
+        self.free_count += 1;
+        old
+    }
+    fn lookup(&self, id: Id<K::Layout>) -> Result<&Slot<T, K::Layout>, LookupError> {
+        if id.tag != self.tag {
+            return Err(LookupError::ForeignArena);
+        }
+        let slot = self.slots.get(id.slot()).ok_or(LookupError::OutOfBounds)?;
+        if id.generation() != slot.generation {
+            return Err(LookupError::Stale {
+                current_generation: slot.generation.to_u64(),
+            });
+        }
+        match slot.entry {
+            Entry::Present(_) => Ok(slot),
+            Entry::Free { .. } | Entry::Quarantined { .. } | Entry::Retired => {
+                Err(LookupError::Vacant)
+            }
+        }
+    }
+}
+/// Like cloning an [`Arena`](crate::Arena), this draws a new tag.
+impl<T: Clone, const N: usize, K: Key<T>> Clone for ArrayArena<T, N, K> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+            first_free: self.first_free,
+            free_count: self.free_count,
+            retired_count: self.retired_count,
+            overflow_policy: self.overflow_policy,
+            tag: ArenaTag::new(),
+        }
+    }
+}
+impl<T: Debug, const N: usize, K: Key<T>> Debug for ArrayArena<T, N, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut f = f.debug_struct("ArrayArena");
+        f.field("slots", &self.slots)
+            .field("first_free", &self.first_free)
+            .field("free_count", &self.free_count)
+            .field("retired_count", &self.retired_count)
+            .field("overflow_policy", &self.overflow_policy);
+        #[cfg(feature = "arena-tags")]
+        f.field("tag", &self.tag);
+        f.finish()
+    }
+}
+impl<T, const N: usize, K: Key<T>> Default for ArrayArena<T, N, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T, const N: usize, K: Key<T>> IntoIterator for ArrayArena<T, N, K> {
+    type Item = T;
+    type IntoIter = IntoIter<T, N, K::Layout>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            length: self.len(),
+            returned: 0,
+            slots: self.slots.into_iter(),
+        }
+    }
+}
+impl<'a, T, const N: usize, K: Key<T>> IntoIterator for &'a ArrayArena<T, N, K> {
+    type IntoIter = Iter<'a, T, K::Layout>;
+    type Item = &'a T;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl<'a, T, const N: usize, K: Key<T>> IntoIterator for &'a mut ArrayArena<T, N, K> {
+    type IntoIter = IterMut<'a, T, K::Layout>;
+    type Item = &'a mut T;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+impl<T, const N: usize, K: Key<T>> Index<K> for ArrayArena<T, N, K> {
+    type Output = T;
+    fn index(&self, key: K) -> &Self::Output {
+        match self.try_get(key) {
+            Ok(item) => item,
+            Err(err) => panic!("Index {} does not exist in ArrayArena: {err}", key.to_id()),
+        }
+    }
+}
+impl<T, const N: usize, K: Key<T>> IndexMut<K> for ArrayArena<T, N, K> {
+    fn index_mut(&mut self, key: K) -> &mut Self::Output {
+        match self.try_get_mut(key) {
+            Ok(item) => item,
+            Err(err) => panic!("Index {} does not exist in ArrayArena: {err}", key.to_id()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct IntoIter<T, const N: usize, L: KeyLayout = U32U32> {
+    slots: std::array::IntoIter<Slot<T, L>, N>,
+    length: usize,
+    returned: usize,
+}
+impl<T, const N: usize, L: KeyLayout> Iterator for IntoIter<T, N, L> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let slot = self.slots.next()?;
+            if let Entry::Present(item) = slot.entry {
+                self.returned += 1;
+                return Some(item);
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.length - self.returned;
+        (length, Some(length))
+    }
+}
+impl<T, const N: usize, L: KeyLayout> DoubleEndedIterator for IntoIter<T, N, L> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            let slot = self.slots.next_back()?;
+            if let Entry::Present(item) = slot.entry {
+                self.returned += 1;
+                return Some(item);
+            }
+        }
+    }
+}
+impl<T, const N: usize, L: KeyLayout> ExactSizeIterator for IntoIter<T, N, L> {}
+impl<T, const N: usize, L: KeyLayout> FusedIterator for IntoIter<T, N, L> {}
+
+#[derive(Clone, Debug)]
+pub struct IntoIterWithIds<T, const N: usize, K: Key<T> = Id> {
+    slots: std::iter::Enumerate<std::array::IntoIter<Slot<T, K::Layout>, N>>,
+    length: usize,
+    returned: usize,
+    tag: ArenaTag,
+}
+impl<T, const N: usize, K: Key<T>> Iterator for IntoIterWithIds<T, N, K> {
+    type Item = (K, T);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, slot) = self.slots.next()?;
+            if let Entry::Present(item) = slot.entry {
+                self.returned += 1;
+                return Some((K::from_id(Id::new(index, slot.generation, self.tag)), item));
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.length - self.returned;
+        (length, Some(length))
+    }
+}
+impl<T, const N: usize, K: Key<T>> DoubleEndedIterator for IntoIterWithIds<T, N, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, slot) = self.slots.next_back()?;
+            if let Entry::Present(item) = slot.entry {
+                self.returned += 1;
+                return Some((K::from_id(Id::new(index, slot.generation, self.tag)), item));
+            }
+        }
+    }
+}
+impl<T, const N: usize, K: Key<T>> ExactSizeIterator for IntoIterWithIds<T, N, K> {}
+impl<T, const N: usize, K: Key<T>> FusedIterator for IntoIterWithIds<T, N, K> {}
+
+#[derive(Debug)]
+pub struct Drain<'a, T, const N: usize, K: Key<T> = Id> {
+    arena: &'a mut ArrayArena<T, N, K>,
+    index: usize,
+    length: usize,
+    returned: usize,
+}
+impl<'a, T, const N: usize, K: Key<T>> Iterator for Drain<'a, T, N, K> {
+    type Item = (K, T);
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < N {
+            let index = self.index;
+            self.index += 1;
+
+            let slot = &self.arena.slots[index];
+            if let Entry::Present(_) = slot.entry {
+                let key = K::from_id(Id::new(index, slot.generation, self.arena.tag));
+                self.returned += 1;
+                return Some((key, self.arena.vacate(index)));
+            }
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.length - self.returned;
+        (length, Some(length))
+    }
+}
+impl<'a, T, const N: usize, K: Key<T>> ExactSizeIterator for Drain<'a, T, N, K> {}
+impl<'a, T, const N: usize, K: Key<T>> FusedIterator for Drain<'a, T, N, K> {}
+impl<'a, T, const N: usize, K: Key<T>> Drop for Drain<'a, T, N, K> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+pub struct ExtractIf<'a, T, const N: usize, F, K: Key<T> = Id> {
+    arena: &'a mut ArrayArena<T, N, K>,
+    index: usize,
+    pred: F,
+}
+impl<'a, T, const N: usize, F, K: Key<T>> Iterator for ExtractIf<'a, T, N, F, K>
+where
+    F: FnMut(K, &mut T) -> bool,
+{
+    type Item = (K, T);
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < N {
+            let index = self.index;
+            self.index += 1;
+
+            let slot = &mut self.arena.slots[index];
+            let key = K::from_id(Id::new(index, slot.generation, self.arena.tag));
+            let Entry::Present(item) = &mut slot.entry else {
+                continue;
+            };
+            if (self.pred)(key, item) {
+                return Some((key, self.arena.vacate(index)));
+            }
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(N - self.index))
+    }
+}
+impl<'a, T, const N: usize, F, K: Key<T>> FusedIterator for ExtractIf<'a, T, N, F, K> where
+    F: FnMut(K, &mut T) -> bool
+{
+}
@@ -0,0 +1,210 @@
+use crate::{FreeListPolicy, Id, Integer, Key, KeyLayout, LookupError, OverflowPolicy, RawArena};
+use std::ops::{Index, IndexMut};
+
+/// An arena that keeps its values packed in a contiguous `Vec<T>`.
+///
+/// Keys resolve through a sparse slot table to a position in the value vector.
+/// Removal swaps the last value into the hole, so the order of values is not
+/// preserved, but iterating never has to skip over vacant slots.
+#[derive(Debug)]
+pub struct DenseArena<T, K: Key<T> = Id> {
+    slots: RawArena<<K::Layout as KeyLayout>::Index, K::Layout>,
+    values: Vec<T>,
+    keys: Vec<K>,
+}
+impl<T, K: Key<T>> DenseArena<T, K> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slots: RawArena::with_capacity(capacity),
+            values: Vec::with_capacity(capacity),
+            keys: Vec::with_capacity(capacity),
+        }
+    }
+    /// Creates an arena whose slot table hands out free slots according to `policy`.
+    pub fn with_free_list_policy(policy: FreeListPolicy) -> Self {
+        Self {
+            slots: RawArena::with_free_list_policy(policy),
+            values: Vec::new(),
+            keys: Vec::new(),
+        }
+    }
+
+    /// Removes every value while invalidating every outstanding key.
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.values.clear();
+        self.keys.clear();
+    }
+
+    pub fn free_list_policy(&self) -> FreeListPolicy {
+        self.slots.free_list_policy()
+    }
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.slots.overflow_policy()
+    }
+    /// Decides what happens to slots whose generation runs out from now on.
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.slots.set_overflow_policy(policy);
+    }
+    /// The number of slots that have been retired because their generation ran out.
+    pub fn retired_count(&self) -> usize {
+        self.slots.retired_count()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    /// Drops the free slots at the end of the slot table and releases unused memory.
+    pub fn shrink_to_fit(&mut self) {
+        self.slots.shrink_to_fit();
+        self.values.shrink_to_fit();
+        self.keys.shrink_to_fit();
+    }
+
+    pub fn insert(&mut self, t: T) -> K {
+        let id = self.slots.insert(Integer::from_usize(self.values.len()));
+        let key = K::from_id(id);
+        self.values.push(t);
+        self.keys.push(key);
+        key
+    }
+    pub fn remove(&mut self, key: K) -> Option<T> {
+        self.try_remove(key).ok()
+    }
+    pub fn try_remove(&mut self, key: K) -> Result<T, LookupError> {
+        let dense = self.slots.try_remove(key.to_id())?.to_usize();
+        let t = self.values.swap_remove(dense);
+        self.keys.swap_remove(dense);
+        if let Some(&moved) = self.keys.get(dense) {
+            *self.slots.get_mut(moved.to_id()).unwrap() = Integer::from_usize(dense);
+        }
+        Ok(t)
+    }
+    pub fn contains(&self, key: K) -> bool {
+        self.slots.contains(key.to_id())
+    }
+
+    pub fn get(&self, key: K) -> Option<&T> {
+        self.try_get(key).ok()
+    }
+    pub fn get_mut(&mut self, key: K) -> Option<&mut T> {
+        self.try_get_mut(key).ok()
+    }
+    pub fn try_get(&self, key: K) -> Result<&T, LookupError> {
+        let dense = self.slots.try_get(key.to_id())?.to_usize();
+        Ok(&self.values[dense])
+    }
+    pub fn try_get_mut(&mut self, key: K) -> Result<&mut T, LookupError> {
+        let dense = self.slots.try_get(key.to_id())?.to_usize();
+        Ok(&mut self.values[dense])
+    }
+
+    /// The values in storage order.
+    pub fn as_slice(&self) -> &[T] {
+        &self.values
+    }
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+    /// The key of each value, in the same order as [`DenseArena::as_slice`].
+    pub fn ids(&self) -> &[K] {
+        &self.keys
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.values.iter()
+    }
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.values.iter_mut()
+    }
+    pub fn iter_with_ids(&self) -> std::iter::Zip<IdsIter<'_, K>, std::slice::Iter<'_, T>> {
+        self.keys.iter().copied().zip(self.values.iter())
+    }
+    pub fn iter_mut_with_ids(
+        &mut self,
+    ) -> std::iter::Zip<IdsIter<'_, K>, std::slice::IterMut<'_, T>> {
+        self.keys.iter().copied().zip(self.values.iter_mut())
+    }
+}
+/// Like cloning an [`Arena`](crate::Arena), this draws a new tag.
+impl<T: Clone, K: Key<T>> Clone for DenseArena<T, K> {
+    fn clone(&self) -> Self {
+        let slots = self.slots.clone();
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| K::from_id(key.to_id().with_tag(slots.tag)))
+            .collect();
+        Self {
+            slots,
+            values: self.values.clone(),
+            keys,
+        }
+    }
+}
+impl<T, K: Key<T>> Default for DenseArena<T, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T, K: Key<T>> IntoIterator for DenseArena<T, K> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter()
+    }
+}
+impl<'a, T, K: Key<T>> IntoIterator for &'a DenseArena<T, K> {
+    type IntoIter = std::slice::Iter<'a, T>;
+    type Item = &'a T;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl<'a, T, K: Key<T>> IntoIterator for &'a mut DenseArena<T, K> {
+    type IntoIter = std::slice::IterMut<'a, T>;
+    type Item = &'a mut T;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+impl<A, K: Key<A>> FromIterator<A> for DenseArena<A, K> {
+    fn from_iter<T: IntoIterator<Item = A>>(iter: T) -> Self {
+        let mut arena = Self::new();
+        arena.extend(iter);
+        arena
+    }
+}
+impl<T, K: Key<T>> Extend<T> for DenseArena<T, K> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+impl<T, K: Key<T>> Index<K> for DenseArena<T, K> {
+    type Output = T;
+    fn index(&self, key: K) -> &Self::Output {
+        match self.try_get(key) {
+            Ok(item) => item,
+            Err(err) => panic!("Index {} does not exist in DenseArena: {err}", key.to_id()),
+        }
+    }
+}
+impl<T, K: Key<T>> IndexMut<K> for DenseArena<T, K> {
+    fn index_mut(&mut self, key: K) -> &mut Self::Output {
+        match self.try_get_mut(key) {
+            Ok(item) => item,
+            Err(err) => panic!("Index {} does not exist in DenseArena: {err}", key.to_id()),
+        }
+    }
+}
+
+pub type IdsIter<'a, K = Id> = std::iter::Copied<std::slice::Iter<'a, K>>;
@@ -0,0 +1,238 @@
+use crate::{Arena, Id, Integer, Key, KeyLayout};
+
+/// A view into a single slot of an [`Arena`], obtained from [`Arena::entry`].
+#[derive(Debug)]
+pub enum Entry<'a, T, K: Key<T> = Id> {
+    Occupied(OccupiedEntry<'a, T, K>),
+    Vacant(VacantEntry<'a, T, K>),
+    Stale(StaleEntry<'a, T, K>),
+}
+impl<'a, T, K: Key<T>> Entry<'a, T, K> {
+    pub(crate) fn new(arena: &'a mut Arena<T, K>, key: K) -> Self {
+        let id = key.to_id();
+        assert!(
+            id.tag == arena.tag,
+            "Index {id} was handed out by a different Arena"
+        );
+        let index = id.slot();
+        let Some(slot) = arena.slots.get(index) else {
+            if id.generation() < arena.generation_watermark {
+                return Self::Stale(StaleEntry { arena, key, id });
+            }
+            return Self::Vacant(VacantEntry { arena, key, id });
+        };
+
+        match slot.entry {
+            crate::Entry::Present(_) if slot.generation == id.generation() => {
+                Self::Occupied(OccupiedEntry { arena, key, id })
+            }
+            crate::Entry::Free { .. } if slot.generation <= id.generation() => {
+                Self::Vacant(VacantEntry { arena, key, id })
+            }
+            _ => Self::Stale(StaleEntry { arena, key, id }),
+        }
+    }
+
+    pub fn key(&self) -> &K {
+        match self {
+            Self::Occupied(entry) => entry.key(),
+            Self::Vacant(entry) => entry.key(),
+            Self::Stale(entry) => entry.key(),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut T)>(mut self, f: F) -> Self {
+        if let Self::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+    /// Returns the value of an occupied entry, inserting `default` into a vacant one.
+    ///
+    /// Returns `None` if the entry is stale, since its id can never become valid again.
+    pub fn or_insert(self, default: T) -> Option<&'a mut T> {
+        self.or_insert_with(|| default)
+    }
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> Option<&'a mut T> {
+        match self {
+            Self::Occupied(entry) => Some(entry.into_mut()),
+            Self::Vacant(entry) => Some(entry.insert(default())),
+            Self::Stale(_) => None,
+        }
+    }
+    pub fn or_default(self) -> Option<&'a mut T>
+    where
+        T: Default,
+    {
+        self.or_insert_with(T::default)
+    }
+}
+
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, T, K: Key<T> = Id> {
+    arena: &'a mut Arena<T, K>,
+    key: K,
+    id: Id<K::Layout>,
+}
+impl<'a, T, K: Key<T>> OccupiedEntry<'a, T, K> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+    pub fn id(&self) -> Id<K::Layout> {
+        self.id
+    }
+
+    pub fn get(&self) -> &T {
+        let crate::Entry::Present(item) = &self.arena.slots[self.id.slot()].entry else {
+            unreachable!()
+        };
+        item
+    }
+    pub fn get_mut(&mut self) -> &mut T {
+        let crate::Entry::Present(item) = &mut self.arena.slots[self.id.slot()].entry else {
+            unreachable!()
+        };
+        item
+    }
+    pub fn into_mut(self) -> &'a mut T {
+        let crate::Entry::Present(item) = &mut self.arena.slots[self.id.slot()].entry else {
+            unreachable!()
+        };
+        item
+    }
+
+    /// Replaces the value while keeping the id valid, returning the old value.
+    pub fn replace(&mut self, value: T) -> T {
+        std::mem::replace(self.get_mut(), value)
+    }
+    #[track_caller]
+    pub fn remove(self) -> T {
+        self.remove_entry().1
+    }
+    #[track_caller]
+    pub fn remove_entry(self) -> (K, T) {
+        let item = self.arena.vacate(self.id.slot());
+        (self.key, item)
+    }
+}
+
+#[derive(Debug)]
+pub struct VacantEntry<'a, T, K: Key<T> = Id> {
+    arena: &'a mut Arena<T, K>,
+    key: K,
+    id: Id<K::Layout>,
+}
+impl<'a, T, K: Key<T>> VacantEntry<'a, T, K> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+    pub fn into_key(self) -> K {
+        self.key
+    }
+    pub fn id(&self) -> Id<K::Layout> {
+        self.id
+    }
+
+    /// Whether the id points past the end of the arena, as opposed to a free slot.
+    pub fn is_out_of_bounds(&self) -> bool {
+        self.id.slot() >= self.arena.slots.len()
+    }
+
+    /// Inserts `value` so that the entry's id resolves to it.
+    ///
+    /// If the id is [out of bounds](Self::is_out_of_bounds), the arena first grows
+    /// to cover its index, and every slot added in between goes onto the free list.
+    ///
+    /// # Panics
+    /// Panics if the arena has reached its [maximum length](Arena::set_max_len).
+    #[track_caller]
+    pub fn insert(self, value: T) -> &'a mut T {
+        if self
+            .arena
+            .max_len
+            .is_some_and(|max_len| self.arena.len() >= max_len)
+        {
+            panic!(
+                "cannot insert into Arena: {}",
+                crate::CapacityError::MaxLenReached
+            );
+        }
+        let index = self.id.slot();
+        self.arena.claim(self.id);
+
+        let slot = &mut self.arena.slots[index];
+        slot.entry = crate::Entry::Present(value);
+        let crate::Entry::Present(item) = &mut slot.entry else {
+            unreachable!()
+        };
+        item
+    }
+}
+
+#[derive(Debug)]
+pub struct StaleEntry<'a, T, K: Key<T> = Id> {
+    arena: &'a mut Arena<T, K>,
+    key: K,
+    id: Id<K::Layout>,
+}
+impl<'a, T, K: Key<T>> StaleEntry<'a, T, K> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+    pub fn into_key(self) -> K {
+        self.key
+    }
+    pub fn id(&self) -> Id<K::Layout> {
+        self.id
+    }
+
+    /// The generation the slot has moved on to.
+    ///
+    /// For a slot that was trimmed off the end of the arena, this is the
+    /// generation it will start at once the arena grows again.
+    pub fn current_generation(&self) -> <K::Layout as KeyLayout>::Generation {
+        match self.arena.slots.get(self.id.slot()) {
+            Some(slot) => slot.generation,
+            None => self.arena.generation_watermark,
+        }
+    }
+    /// Whether the slot currently holds a value under a different generation.
+    pub fn is_occupied(&self) -> bool {
+        matches!(
+            self.arena.slots.get(self.id.slot()),
+            Some(crate::Slot {
+                entry: crate::Entry::Present(_),
+                ..
+            })
+        )
+    }
+    /// Whether the slot ran out of generations and will never hold a value again.
+    pub fn is_retired(&self) -> bool {
+        matches!(
+            self.arena.slots.get(self.id.slot()),
+            Some(crate::Slot {
+                entry: crate::Entry::Retired,
+                ..
+            })
+        )
+    }
+}
+
+impl<T, K: Key<T>> Arena<T, K> {
+    /// Takes the free slot at `id.index()` off the free list, growing the arena
+    /// if needed, and advances its generation to `id.generation()`.
+    fn claim(&mut self, id: Id<K::Layout>) {
+        let target = id.slot();
+        while self.slots.len() <= target {
+            self.slots.push(crate::Slot {
+                generation: self.generation_watermark,
+                entry: crate::Entry::Free {
+                    next_free: Integer::ZERO,
+                },
+            });
+            self.push_free(self.slots.len() - 1);
+        }
+        self.unlink_free(target);
+        self.slots[target].generation = id.generation();
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{Arena, Entry, Integer, Key, KeyLayout};
+use std::cmp::Reverse;
+
+/// Decides which free slot an [`Arena`] hands out next.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FreeListPolicy {
+    /// Reuse the most recently freed slot, which keeps recently touched memory hot.
+    #[default]
+    Lifo,
+    /// Reuse the slot that has been free the longest, which spreads generation
+    /// wear across slots and keeps stale ids detectable for longer.
+    Fifo,
+    /// Reuse the free slot with the lowest index, which keeps values packed
+    /// towards the front of the arena.
+    LowestIndex,
+}
+
+impl<T, K: Key<T>> Arena<T, K> {
+    /// Adds the slot at `index` to the free list. Its entry must already be free.
+    pub(crate) fn push_free(&mut self, index: usize) {
+        debug_assert!(matches!(self.slots[index].entry, Entry::Free { .. }));
+        let next_free = match self.free_list_policy {
+            FreeListPolicy::Lifo => {
+                let next_free = self.first_free;
+                self.first_free = Integer::from_usize(index);
+                next_free
+            }
+            FreeListPolicy::Fifo => {
+                if self.free_count == 0 {
+                    self.first_free = Integer::from_usize(index);
+                } else {
+                    let last = self.last_free.to_usize();
+                    self.slots[last].entry = Entry::Free {
+                        next_free: Integer::from_usize(index),
+                    };
+                }
+                self.last_free = Integer::from_usize(index);
+                Integer::ZERO
+            }
+            FreeListPolicy::LowestIndex => {
+                self.free_heap.push(Reverse(Integer::from_usize(index)));
+                Integer::ZERO
+            }
+        };
+        self.slots[index].entry = Entry::Free { next_free };
+        self.free_count += 1;
+    }
+    /// The slot that [`Arena::pop_free`] would take.
+    pub(crate) fn peek_free(&self) -> Option<usize> {
+        if self.free_count == 0 {
+            return None;
+        }
+        match self.free_list_policy {
+            FreeListPolicy::Lifo | FreeListPolicy::Fifo => Some(self.first_free.to_usize()),
+            FreeListPolicy::LowestIndex => self.free_heap.peek().map(|index| index.0.to_usize()),
+        }
+    }
+    /// Takes the next slot off the free list.
+    pub(crate) fn pop_free(&mut self) -> Option<usize> {
+        let index = self.peek_free()?;
+        match self.free_list_policy {
+            FreeListPolicy::Lifo | FreeListPolicy::Fifo => {
+                self.first_free = self.next_free(index);
+            }
+            FreeListPolicy::LowestIndex => {
+                self.free_heap.pop();
+            }
+        }
+        self.free_count -= 1;
+        Some(index)
+    }
+    /// Takes the slot at `target` off the free list, wherever it is.
+    ///
+    /// This walks the free list, so it takes time linear in the number of free slots.
+    pub(crate) fn unlink_free(&mut self, target: usize) {
+        match self.free_list_policy {
+            FreeListPolicy::Lifo | FreeListPolicy::Fifo => {
+                let next_free = self.next_free(target);
+                if self.first_free.to_usize() == target {
+                    self.first_free = next_free;
+                } else {
+                    let mut prev = self.first_free.to_usize();
+                    loop {
+                        let next = self.next_free(prev).to_usize();
+                        if next == target {
+                            break;
+                        }
+                        prev = next;
+                    }
+                    self.slots[prev].entry = Entry::Free { next_free };
+                    if self.last_free.to_usize() == target {
+                        self.last_free = Integer::from_usize(prev);
+                    }
+                }
+            }
+            FreeListPolicy::LowestIndex => {
+                self.free_heap.retain(|index| index.0.to_usize() != target);
+            }
+        }
+        self.free_count -= 1;
+    }
+    /// Threads every free slot onto a new free list, in index order.
+    pub(crate) fn rebuild_free_list(&mut self) {
+        self.first_free = Integer::ZERO;
+        self.last_free = Integer::ZERO;
+        self.free_heap.clear();
+        self.free_count = 0;
+
+        let is_free = |slot: &crate::Slot<T, K::Layout>| matches!(slot.entry, Entry::Free { .. });
+        if self.free_list_policy == FreeListPolicy::Lifo {
+            for index in (0..self.slots.len()).rev() {
+                if is_free(&self.slots[index]) {
+                    self.push_free(index);
+                }
+            }
+        } else {
+            for index in 0..self.slots.len() {
+                if is_free(&self.slots[index]) {
+                    self.push_free(index);
+                }
+            }
+        }
+    }
+
+    /// Puts the slot at `index` into quarantine, releasing the oldest quarantined
+    /// slot if the quarantine is full. Its entry must already be quarantined.
+    pub(crate) fn quarantine(&mut self, index: usize) {
+        self.quarantine.push_back(Integer::from_usize(index));
+        if self.quarantine.len() > self.quarantine_len {
+            self.release_quarantined();
+        }
+    }
+    /// Moves the oldest quarantined slot onto the free list.
+    pub(crate) fn release_quarantined(&mut self) {
+        let Some(index) = self.quarantine.pop_front() else {
+            return;
+        };
+        let index = index.to_usize();
+        self.slots[index].entry = Entry::Free {
+            next_free: Integer::ZERO,
+        };
+        self.push_free(index);
+    }
+
+    fn next_free(&self, index: usize) -> <K::Layout as KeyLayout>::Index {
+        let Entry::Free { next_free } = self.slots[index].entry else {
+            unreachable!()
+        };
+        next_free
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{Arena, AsId, Id, Integer, Key};
+use std::{
+    fmt::Debug,
+    iter::FusedIterator,
+    marker::PhantomData,
+    ops::{BitAnd, BitOr, Sub},
+};
+
+/// A set of arena keys, stored as a bitset over slot indices.
+///
+/// Next to each set bit the set remembers the id it was inserted under, so a
+/// key whose slot has since been reused is not considered a member.
+pub struct IdSet<K: AsId = Id> {
+    bits: Vec<u64>,
+    /// The id stored at each index whose bit is set. Entries without a bit are
+    /// leftovers and carry no meaning.
+    ids: Vec<Id<K::Layout>>,
+    len: usize,
+    _key: PhantomData<fn() -> K>,
+}
+impl<K: AsId> IdSet<K> {
+    pub fn new() -> Self {
+        Self {
+            bits: Vec::new(),
+            ids: Vec::new(),
+            len: 0,
+            _key: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn clear(&mut self) {
+        self.bits.clear();
+        self.ids.clear();
+        self.len = 0;
+    }
+
+    /// Adds `key` to the set, returning whether it was newly added.
+    ///
+    /// A key stored for an older generation of the same slot is replaced. If the
+    /// set already holds a newer generation, `key` is stale and is not added.
+    pub fn insert(&mut self, key: K) -> bool {
+        let id = key.to_id();
+        let index = id.index().to_usize();
+        // `intersect_with` may leave `bits` shorter than `ids`.
+        if self.ids.len() <= index {
+            self.ids.resize(index + 1, id);
+        }
+        if self.bits.len() <= index / 64 {
+            self.bits.resize(index / 64 + 1, 0);
+        }
+
+        if self.has_bit(index) {
+            let old = self.ids[index];
+            if old == id || old.generation() > id.generation() {
+                return false;
+            }
+        } else {
+            self.bits[index / 64] |= 1 << (index % 64);
+            self.len += 1;
+        }
+        self.ids[index] = id;
+        true
+    }
+    /// Removes `key` from the set, returning whether it was a member.
+    pub fn remove(&mut self, key: K) -> bool {
+        if !self.contains(key) {
+            return false;
+        }
+        let index = key.to_id().index().to_usize();
+        self.bits[index / 64] &= !(1 << (index % 64));
+        self.len -= 1;
+        true
+    }
+    pub fn contains(&self, key: K) -> bool {
+        let id = key.to_id();
+        let index = id.index().to_usize();
+        self.has_bit(index) && self.ids[index] == id
+    }
+
+    /// Adds every key of `other`, keeping the newer generation where both sets
+    /// hold a key for the same slot.
+    pub fn union_with(&mut self, other: &Self) {
+        for key in other {
+            self.insert(key);
+        }
+    }
+    /// Keeps only the keys that are also in `other`.
+    pub fn intersect_with(&mut self, other: &Self) {
+        self.bits.truncate(other.bits.len());
+        for (word, &other_word) in self.bits.iter_mut().zip(&other.bits) {
+            *word &= other_word;
+        }
+        self.retain(|id| other.ids[id.index().to_usize()] == id);
+    }
+    /// Removes every key that is also in `other`.
+    pub fn difference_with(&mut self, other: &Self) {
+        for key in other {
+            self.remove(key);
+        }
+    }
+
+    /// Removes every key that no longer resolves to a value in `arena`.
+    pub fn prune<T, A: Key<T, Layout = K::Layout>>(&mut self, arena: &Arena<T, A>) {
+        let arena = arena.raw();
+        self.retain(|id| arena.contains(id));
+    }
+
+    /// Iterates over the keys in order of their index.
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter {
+            set: self,
+            word: self.bits.first().copied().unwrap_or(0),
+            word_index: 0,
+            returned: 0,
+        }
+    }
+
+    fn has_bit(&self, index: usize) -> bool {
+        self.bits
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+    /// Keeps the set bits for which `f` returns `true` and recounts the length.
+    fn retain<F: FnMut(Id<K::Layout>) -> bool>(&mut self, mut f: F) {
+        self.len = 0;
+        for (word_index, word) in self.bits.iter_mut().enumerate() {
+            let mut bits = *word;
+            while bits != 0 {
+                let bit = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+
+                let index = word_index * 64 + bit;
+                if f(self.ids[index]) {
+                    self.len += 1;
+                } else {
+                    *word &= !(1 << bit);
+                }
+            }
+        }
+    }
+}
+impl<K: AsId> Clone for IdSet<K> {
+    fn clone(&self) -> Self {
+        Self {
+            bits: self.bits.clone(),
+            ids: self.ids.clone(),
+            len: self.len,
+            _key: PhantomData,
+        }
+    }
+}
+impl<K: AsId + Debug> Debug for IdSet<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self).finish()
+    }
+}
+impl<K: AsId> Default for IdSet<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<K: AsId> PartialEq for IdSet<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().all(|key| other.contains(key))
+    }
+}
+impl<K: AsId> Eq for IdSet<K> {}
+impl<'a, K: AsId> IntoIterator for &'a IdSet<K> {
+    type IntoIter = Iter<'a, K>;
+    type Item = K;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl<K: AsId> FromIterator<K> for IdSet<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+impl<K: AsId> Extend<K> for IdSet<K> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+impl<K: AsId> BitOr for &IdSet<K> {
+    type Output = IdSet<K>;
+    fn bitor(self, other: Self) -> Self::Output {
+        let mut set = self.clone();
+        set.union_with(other);
+        set
+    }
+}
+impl<K: AsId> BitAnd for &IdSet<K> {
+    type Output = IdSet<K>;
+    fn bitand(self, other: Self) -> Self::Output {
+        let mut set = self.clone();
+        set.intersect_with(other);
+        set
+    }
+}
+impl<K: AsId> Sub for &IdSet<K> {
+    type Output = IdSet<K>;
+    fn sub(self, other: Self) -> Self::Output {
+        let mut set = self.clone();
+        set.difference_with(other);
+        set
+    }
+}
+
+pub struct Iter<'a, K: AsId> {
+    set: &'a IdSet<K>,
+    /// The bits of the current word that have not been visited yet.
+    word: u64,
+    word_index: usize,
+    returned: usize,
+}
+impl<'a, K: AsId> Iterator for Iter<'a, K> {
+    type Item = K;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.word == 0 {
+            self.word_index += 1;
+            self.word = *self.set.bits.get(self.word_index)?;
+        }
+        let bit = self.word.trailing_zeros() as usize;
+        self.word &= self.word - 1;
+        self.returned += 1;
+        Some(K::from_id(self.set.ids[self.word_index * 64 + bit]))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.set.len - self.returned;
+        (length, Some(length))
+    }
+}
+impl<'a, K: AsId> ExactSizeIterator for Iter<'a, K> {}
+impl<'a, K: AsId> FusedIterator for Iter<'a, K> {}
+impl<'a, K: AsId> Clone for Iter<'a, K> {
+    fn clone(&self) -> Self {
+        Self {
+            set: self.set,
+            word: self.word,
+            word_index: self.word_index,
+            returned: self.returned,
+        }
+    }
+}
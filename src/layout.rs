@@ -0,0 +1,182 @@
+use std::{
+    fmt::{Debug, Display},
+    hash::Hash,
+    num::{NonZeroU16, NonZeroU32, NonZeroU64},
+};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// An unsigned integer that can serve as the index or generation of an [`Id`](crate::Id).
+pub trait Integer:
+    sealed::Sealed + Copy + Eq + Ord + Hash + Debug + Display + Default + Send + Sync + 'static
+{
+    const ZERO: Self;
+    fn to_usize(self) -> usize;
+    fn to_u64(self) -> u64;
+    /// Converts `n`, truncating it if it does not fit.
+    fn from_usize(n: usize) -> Self;
+    /// Converts `n`, truncating it if it does not fit.
+    fn from_u64(n: u64) -> Self;
+}
+macro_rules! impl_integer {
+    ($($t:ty),*) => {$(
+        impl sealed::Sealed for $t {}
+        impl Integer for $t {
+            const ZERO: Self = 0;
+            fn to_usize(self) -> usize {
+                self as usize
+            }
+            fn to_u64(self) -> u64 {
+                self as u64
+            }
+            fn from_usize(n: usize) -> Self {
+                n as Self
+            }
+            fn from_u64(n: u64) -> Self {
+                n as Self
+            }
+        }
+    )*};
+}
+impl_integer!(u16, u32, u64);
+
+/// Decides how wide the index and generation of an [`Id`](crate::Id) are,
+/// and how the two are stored inside it.
+pub trait KeyLayout: Copy + Eq + Hash + Debug + Default + Send + Sync + 'static {
+    type Index: Integer;
+    type Generation: Integer;
+    /// What an `Id` actually stores.
+    ///
+    /// The provided layouts all store the bitwise complement of the index in a
+    /// non-zero integer, so that `Option<Id>` is no larger than `Id`. This costs
+    /// them the single largest index.
+    type Repr: Copy + Eq + Hash + Send + Sync + 'static;
+
+    const MAX_INDEX: Self::Index;
+    const MAX_GENERATION: Self::Generation;
+
+    fn pack(index: Self::Index, generation: Self::Generation) -> Self::Repr;
+    fn index(repr: Self::Repr) -> Self::Index;
+    fn generation(repr: Self::Repr) -> Self::Generation;
+}
+
+/// 16-bit indices with 16-bit generations, for 4-byte ids.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct U16U16;
+impl KeyLayout for U16U16 {
+    type Index = u16;
+    type Generation = u16;
+    type Repr = (NonZeroU16, u16);
+
+    const MAX_INDEX: u16 = u16::MAX - 1;
+    const MAX_GENERATION: u16 = u16::MAX;
+
+    fn pack(index: u16, generation: u16) -> Self::Repr {
+        let index = NonZeroU16::new(!index).expect("index out of range");
+        (index, generation)
+    }
+    fn index(repr: Self::Repr) -> u16 {
+        !repr.0.get()
+    }
+    fn generation(repr: Self::Repr) -> u16 {
+        repr.1
+    }
+}
+
+/// 32-bit indices with 32-bit generations, for 8-byte ids. This is the default.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct U32U32;
+impl KeyLayout for U32U32 {
+    type Index = u32;
+    type Generation = u32;
+    type Repr = (NonZeroU32, u32);
+
+    const MAX_INDEX: u32 = u32::MAX - 1;
+    const MAX_GENERATION: u32 = u32::MAX;
+
+    fn pack(index: u32, generation: u32) -> Self::Repr {
+        let index = NonZeroU32::new(!index).expect("index out of range");
+        (index, generation)
+    }
+    fn index(repr: Self::Repr) -> u32 {
+        !repr.0.get()
+    }
+    fn generation(repr: Self::Repr) -> u32 {
+        repr.1
+    }
+}
+
+/// 32-bit indices with 64-bit generations that will never run out in practice.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct U32U64;
+impl KeyLayout for U32U64 {
+    type Index = u32;
+    type Generation = u64;
+    type Repr = (NonZeroU32, u64);
+
+    const MAX_INDEX: u32 = u32::MAX - 1;
+    const MAX_GENERATION: u64 = u64::MAX;
+
+    fn pack(index: u32, generation: u64) -> Self::Repr {
+        let index = NonZeroU32::new(!index).expect("index out of range");
+        (index, generation)
+    }
+    fn index(repr: Self::Repr) -> u32 {
+        !repr.0.get()
+    }
+    fn generation(repr: Self::Repr) -> u64 {
+        repr.1
+    }
+}
+
+/// 24-bit indices with 40-bit generations, packed together into a single `u64`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Packed64;
+impl Packed64 {
+    const INDEX_BITS: u32 = 24;
+    const INDEX_MASK: u64 = (1 << Self::INDEX_BITS) - 1;
+}
+impl KeyLayout for Packed64 {
+    type Index = u32;
+    type Generation = u64;
+    type Repr = NonZeroU64;
+
+    const MAX_INDEX: u32 = Self::INDEX_MASK as u32 - 1;
+    const MAX_GENERATION: u64 = (1 << (64 - Self::INDEX_BITS)) - 1;
+
+    fn pack(index: u32, generation: u64) -> Self::Repr {
+        debug_assert!(generation <= Self::MAX_GENERATION);
+        let index = !index as u64 & Self::INDEX_MASK;
+        NonZeroU64::new((generation << Self::INDEX_BITS) | index).expect("index out of range")
+    }
+    fn index(repr: Self::Repr) -> u32 {
+        (!repr.get() & Self::INDEX_MASK) as u32
+    }
+    fn generation(repr: Self::Repr) -> u64 {
+        repr.get() >> Self::INDEX_BITS
+    }
+}
+
+/// The generation following `generation`, wrapping around to zero after the maximum.
+pub(crate) fn next_generation<L: KeyLayout>(generation: L::Generation) -> L::Generation {
+    if generation == L::MAX_GENERATION {
+        L::Generation::ZERO
+    } else {
+        L::Generation::from_u64(generation.to_u64() + 1)
+    }
+}
+
+// Every provided layout leaves a niche, so that `Option<Id>` costs nothing extra.
+const _: () = {
+    use crate::{Id, TypedId};
+    use std::mem::size_of;
+
+    assert!(size_of::<Option<Id<U16U16>>>() == size_of::<Id<U16U16>>());
+    assert!(size_of::<Option<Id<U32U32>>>() == size_of::<Id<U32U32>>());
+    assert!(size_of::<Option<Id<U32U64>>>() == size_of::<Id<U32U64>>());
+    assert!(size_of::<Option<Id<Packed64>>>() == size_of::<Id<Packed64>>());
+    assert!(size_of::<Option<TypedId<(), U32U32>>>() == size_of::<TypedId<(), U32U32>>());
+    assert!(size_of::<Option<TypedId<(), Packed64>>>() == size_of::<TypedId<(), Packed64>>());
+};
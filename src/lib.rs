@@ -1,307 +1,1002 @@
+pub mod array;
+mod dense;
+pub mod entry;
+mod free_list;
+pub mod id_set;
+mod layout;
+mod macros;
+mod remap;
+pub mod secondary;
+pub mod sparse_secondary;
+mod tag;
+#[cfg(test)]
+mod tests;
+mod typed;
+
+pub use array::ArrayArena;
+pub use dense::{DenseArena, IdsIter};
+pub use free_list::FreeListPolicy;
+pub use id_set::IdSet;
+pub use layout::{Integer, KeyLayout, Packed64, U16U16, U32U32, U32U64};
+pub use remap::{Remap, Remappable};
+pub use secondary::SecondaryMap;
+pub use sparse_secondary::SparseSecondaryMap;
+pub use typed::TypedId;
+
+use tag::ArenaTag;
+
+#[cfg(feature = "derive")]
+pub use gen_arena_derive::GenIndex;
+
 use std::{
-    fmt::Display,
+    cmp::Reverse,
+    collections::{BinaryHeap, TryReserveError, VecDeque},
+    convert::Infallible,
+    error::Error,
+    fmt::{Debug, Display},
     iter::FusedIterator,
+    marker::PhantomData,
     ops::{Index, IndexMut},
 };
 
-#[derive(Clone, Debug)]
-pub struct Arena<T> {
-    slots: Vec<Slot<T>>,
-    first_free: u32,
-    free_count: u32,
+/// Storage for values of type `T`, handing out keys of type `K`.
+///
+/// With the default `K = Id`, any raw [`Id`] can index the arena. Choosing a
+/// typed key such as [`TypedId<T>`] or a `#[derive(GenIndex)]` newtype makes the
+/// safe API accept only that key; raw ids then have to go through [`Arena::raw`].
+#[repr(C)]
+pub struct Arena<T, K: Key<T> = Id> {
+    slots: Vec<Slot<T, K::Layout>>,
+    first_free: <K::Layout as KeyLayout>::Index,
+    /// The end of the free list, only maintained under [`FreeListPolicy::Fifo`].
+    last_free: <K::Layout as KeyLayout>::Index,
+    /// The free slots under [`FreeListPolicy::LowestIndex`], which does not use
+    /// the free list threaded through the slots.
+    free_heap: BinaryHeap<Reverse<<K::Layout as KeyLayout>::Index>>,
+    free_count: usize,
+    free_list_policy: FreeListPolicy,
+    /// Freed slots that are held back from the free list, oldest first.
+    quarantine: VecDeque<<K::Layout as KeyLayout>::Index>,
+    quarantine_len: usize,
+    poison_quarantine: bool,
+    retired_count: usize,
+    /// The generation new slots start at, so that keys to slots trimmed off
+    /// the end stay stale once the arena grows again.
+    generation_watermark: <K::Layout as KeyLayout>::Generation,
+    max_len: Option<usize>,
+    overflow_policy: OverflowPolicy,
+    tag: ArenaTag,
+    _key: PhantomData<fn() -> K>,
 }
-impl<T> Arena<T> {
+/// An arena that is indexed by raw [`Id`]s.
+pub type RawArena<T, L = U32U32> = Arena<T, Id<L>>;
+
+impl<T, K: Key<T>> Arena<T, K> {
     pub fn new() -> Self {
         Self::with_capacity(0)
     }
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             slots: Vec::with_capacity(capacity),
-            first_free: 0,
+            first_free: Integer::ZERO,
+            last_free: Integer::ZERO,
+            free_heap: BinaryHeap::new(),
             free_count: 0,
+            free_list_policy: FreeListPolicy::default(),
+            quarantine: VecDeque::new(),
+            quarantine_len: 0,
+            poison_quarantine: false,
+            retired_count: 0,
+            generation_watermark: Integer::ZERO,
+            max_len: None,
+            overflow_policy: OverflowPolicy::default(),
+            tag: ArenaTag::new(),
+            _key: PhantomData,
         }
     }
+    /// Creates an arena that hands out free slots according to `policy`.
+    pub fn with_free_list_policy(policy: FreeListPolicy) -> Self {
+        Self {
+            free_list_policy: policy,
+            ..Self::new()
+        }
+    }
+
+    /// Fails to compile if `Arena<T, K>` and `RawArena<T, K::Layout>` differ in
+    /// size or alignment, which `raw` and `raw_mut` rely on.
+    const SAME_LAYOUT_AS_RAW: () = assert!(
+        std::mem::size_of::<Self>() == std::mem::size_of::<RawArena<T, K::Layout>>()
+            && std::mem::align_of::<Self>() == std::mem::align_of::<RawArena<T, K::Layout>>()
+    );
 
+    /// Gives access to the arena through raw [`Id`]s, bypassing the key type.
+    pub fn raw(&self) -> &RawArena<T, K::Layout> {
+        let () = Self::SAME_LAYOUT_AS_RAW;
+        // SAFETY: `Arena` is `repr(C)` and `K` only appears in a zero-sized marker,
+        // so every choice of key with the same layout results in the same layout.
+        unsafe { &*(self as *const Self as *const RawArena<T, K::Layout>) }
+    }
+    /// Gives mutable access to the arena through raw [`Id`]s, bypassing the key type.
+    pub fn raw_mut(&mut self) -> &mut RawArena<T, K::Layout> {
+        let () = Self::SAME_LAYOUT_AS_RAW;
+        // SAFETY: See `raw`.
+        unsafe { &mut *(self as *mut Self as *mut RawArena<T, K::Layout>) }
+    }
+    pub fn into_raw(self) -> RawArena<T, K::Layout> {
+        self.rekey()
+    }
+    /// Reinterprets a raw arena as one keyed by `K`.
+    pub fn from_raw(raw: RawArena<T, K::Layout>) -> Self {
+        raw.rekey()
+    }
+    fn rekey<K2: Key<T, Layout = K::Layout>>(self) -> Arena<T, K2> {
+        Arena {
+            slots: self.slots,
+            first_free: self.first_free,
+            last_free: self.last_free,
+            free_heap: self.free_heap,
+            free_count: self.free_count,
+            free_list_policy: self.free_list_policy,
+            quarantine: self.quarantine,
+            quarantine_len: self.quarantine_len,
+            poison_quarantine: self.poison_quarantine,
+            retired_count: self.retired_count,
+            generation_watermark: self.generation_watermark,
+            max_len: self.max_len,
+            overflow_policy: self.overflow_policy,
+            tag: self.tag,
+            _key: PhantomData,
+        }
+    }
+
+    /// Removes every value while keeping the slots, so every key handed out
+    /// before the call is rejected afterwards.
     pub fn clear(&mut self) {
+        for slot in &mut self.slots {
+            match slot.entry {
+                Entry::Retired => continue,
+                Entry::Present(_) if self.overflow_policy.retires::<K::Layout>(slot.generation) => {
+                    slot.entry = Entry::Retired;
+                    self.retired_count += 1;
+                    continue;
+                }
+                Entry::Present(_) => {
+                    slot.generation = layout::next_generation::<K::Layout>(slot.generation);
+                }
+                Entry::Free { .. } | Entry::Quarantined { .. } => (),
+            }
+            slot.entry = Entry::Free {
+                next_free: Integer::ZERO,
+            };
+        }
+        self.quarantine.clear();
+        self.rebuild_free_list();
+    }
+    /// Removes every value and forgets all generations.
+    ///
+    /// Unlike [`Arena::clear`], keys handed out before the call are *not*
+    /// invalidated: they may resolve to values inserted afterwards.
+    pub fn reset(&mut self) {
         self.slots.clear();
-        self.free_count = 0;
+        self.quarantine.clear();
+        self.rebuild_free_list();
+        self.retired_count = 0;
+        self.generation_watermark = Integer::ZERO;
     }
-    pub fn reserve(&mut self, additional: usize) {
-        let free = self.free_count as usize;
-        if additional <= free {
-            return;
+
+    pub fn free_list_policy(&self) -> FreeListPolicy {
+        self.free_list_policy
+    }
+    pub fn quarantine_len(&self) -> usize {
+        self.quarantine_len
+    }
+    /// Holds up to `len` freed slots back from reuse, releasing the oldest one
+    /// to the free list whenever the quarantine is full.
+    ///
+    /// Quarantined slots keep their new generation, so a freshly inserted value
+    /// never lands in a slot that was freed within the last `len` removals.
+    pub fn set_quarantine_len(&mut self, len: usize) {
+        self.quarantine_len = len;
+        while self.quarantine.len() > len {
+            self.release_quarantined();
         }
-        self.slots.reserve(free - additional);
+    }
+    /// Makes any access through a key to a quarantined slot panic with the
+    /// location the value was removed at, instead of failing quietly.
+    ///
+    /// [`Arena::contains`] still just returns `false`. Only has an effect in
+    /// debug builds.
+    ///
+    /// Values removed through [`Arena::drain`] or [`Arena::extract_if`] report
+    /// a location inside this crate, since iterators cannot pass on where they
+    /// were called from.
+    pub fn set_quarantine_poisoning(&mut self, poison: bool) {
+        self.poison_quarantine = poison;
+    }
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+    /// Limits how many values the arena holds at once; inserting beyond the
+    /// limit fails. Values already in the arena are kept either way.
+    pub fn set_max_len(&mut self, max_len: Option<usize>) {
+        self.max_len = max_len;
+    }
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow_policy
+    }
+    /// Decides what happens to slots whose generation runs out from now on.
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.overflow_policy = policy;
+    }
+    /// The number of slots that have been retired because their generation ran out.
+    pub fn retired_count(&self) -> usize {
+        self.retired_count
+    }
+    /// The number of values the arena can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity() - self.quarantine.len() - self.retired_count
+    }
+    /// The number of free slots that are ready to be reused.
+    pub fn vacant_count(&self) -> usize {
+        self.free_count
+    }
+    /// Makes room for at least `additional` more values, counting free slots.
+    ///
+    /// This also makes room to track every slot once it is freed, so neither
+    /// inserting nor removing allocates until the arena outgrows its capacity.
+    /// Changing the [quarantine length](Arena::set_quarantine_len) afterwards
+    /// may need another call.
+    pub fn reserve(&mut self, additional: usize) {
+        self.slots
+            .reserve(additional.saturating_sub(self.free_count));
+        self.reserve_free_lists();
     }
     pub fn reserve_exact(&mut self, additional: usize) {
-        let free = self.free_count as usize;
-        if additional <= free {
-            return;
-        }
-        self.slots.reserve_exact(free - additional);
+        self.slots
+            .reserve_exact(additional.saturating_sub(self.free_count));
+        self.reserve_free_lists();
+    }
+    /// Like [`Arena::reserve`], but returns an error instead of panicking or
+    /// aborting if the memory cannot be allocated.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.slots
+            .try_reserve(additional.saturating_sub(self.free_count))?;
+        self.try_reserve_free_lists()
+    }
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.slots
+            .try_reserve_exact(additional.saturating_sub(self.free_count))?;
+        self.try_reserve_free_lists()
+    }
+    /// Drops the free slots at the end of the arena and releases unused memory.
+    ///
+    /// The arena remembers the generations of the dropped slots, so keys to them
+    /// keep being rejected after the arena grows again.
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_to(0);
+    }
+    /// Like [`Arena::shrink_to_fit`], but keeps room for at least `min_capacity` slots.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.trim_free_slots(min_capacity);
+        self.rebuild_free_list();
+        self.slots.shrink_to(min_capacity);
     }
 
     pub fn len(&self) -> usize {
-        let free_count = self.free_count as usize;
-        self.slots.len() - free_count
+        self.slots.len() - self.free_count - self.quarantine.len() - self.retired_count
     }
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
-    pub fn insert<I: GenIndex<Item = T>>(&mut self, t: T) -> I {
-        let raw = self.insert_raw(t);
-        I::from(raw)
+    /// # Panics
+    /// Panics if the arena is full, see [`Arena::try_insert`].
+    #[track_caller]
+    pub fn insert(&mut self, t: T) -> K {
+        match self.try_insert(t) {
+            Ok(key) => key,
+            Err(err) => panic!("{err}"),
+        }
     }
-    pub fn remove<I: GenIndex<Item = T>>(&mut self, id: I) -> Option<T> {
-        self.remove_raw(id.get_id())
+    /// Inserts `t`, or hands it back if the arena has reached its
+    /// [maximum length](Arena::set_max_len) or run out of indices.
+    pub fn try_insert(&mut self, t: T) -> Result<K, InsertError<T>> {
+        if let Err(kind) = self.check_room() {
+            return Err(InsertError { kind, value: t });
+        }
+        let id = self.free_index();
+        self.slots[id.slot()].entry = Entry::Present(t);
+
+        Ok(K::from_id(id))
     }
-    pub fn contains<I: GenIndex<Item = T>>(&self, id: I) -> bool {
-        self.exists_raw(id.get_id())
+    /// Inserts the value returned by `f`, which is given the key the value will receive.
+    ///
+    /// # Panics
+    /// Panics if the arena is full, see [`Arena::try_insert`].
+    #[track_caller]
+    pub fn insert_with<F: FnOnce(K) -> T>(&mut self, f: F) -> K {
+        match self.try_insert_with(|key| Ok::<T, Infallible>(f(key))) {
+            Ok(key) => key,
+            Err(InsertWithError::Full(kind)) => panic!("cannot insert into Arena: {kind}"),
+            Err(InsertWithError::Failed(never)) => match never {},
+        }
     }
+    /// Like [`Arena::insert_with`], but `f` may fail, and a full arena is
+    /// reported instead of panicking. In that case `f` is not called.
+    ///
+    /// If `f` returns an error or panics, the arena is left untouched.
+    pub fn try_insert_with<E, F>(&mut self, f: F) -> Result<K, InsertWithError<E>>
+    where
+        F: FnOnce(K) -> Result<T, E>,
+    {
+        self.check_room().map_err(InsertWithError::Full)?;
+        let id = self.next_id();
+        let t = f(K::from_id(id)).map_err(InsertWithError::Failed)?;
+
+        let allocated = self.free_index();
+        debug_assert_eq!(id, allocated);
+        self.slots[id.slot()].entry = Entry::Present(t);
 
-    pub fn get<I: GenIndex<Item = T>>(&mut self, id: I) -> Option<&T> {
-        self.get_raw(id.get_id())
+        Ok(K::from_id(id))
     }
-    pub fn get_mut<I: GenIndex<Item = T>>(&mut self, id: I) -> Option<&mut T> {
-        self.get_mut_raw(id.get_id())
+    #[track_caller]
+    pub fn remove(&mut self, key: K) -> Option<T> {
+        self.try_remove(key).ok()
+    }
+    #[track_caller]
+    pub fn try_remove(&mut self, key: K) -> Result<T, LookupError> {
+        let id = key.to_id();
+        self.access(id)?;
+        Ok(self.vacate(id.slot()))
+    }
+    pub fn contains(&self, key: K) -> bool {
+        self.lookup(key.to_id()).is_ok()
     }
 
-    pub fn insert_raw(&mut self, t: T) -> Id {
-        let index = self.free_index();
-        self.slots[index.index as usize].entry = Entry::Present(t);
-
-        index
+    pub fn get(&self, key: K) -> Option<&T> {
+        self.try_get(key).ok()
     }
-    pub fn remove_raw(&mut self, id: Id) -> Option<T> {
-        if !self.exists_raw(id) {
-            return None;
+    pub fn get_mut(&mut self, key: K) -> Option<&mut T> {
+        self.try_get_mut(key).ok()
+    }
+    pub fn try_get(&self, key: K) -> Result<&T, LookupError> {
+        let slot = self.access(key.to_id())?;
+        let Entry::Present(item) = &slot.entry else {
+            unreachable!()
         };
-
-        let item = &mut self.slots[id.index as usize];
-        item.generation += 1;
-
-        let old = item.entry.take(self.first_free);
-        self.first_free = id.index;
-        self.free_count += 1;
-
-        old
+        Ok(item)
+    }
+    pub fn try_get_mut(&mut self, key: K) -> Result<&mut T, LookupError> {
+        let id = key.to_id();
+        self.access(id)?;
+        let Entry::Present(item) = &mut self.slots[id.slot()].entry else {
+            unreachable!()
+        };
+        Ok(item)
     }
-    pub fn exists_raw(&self, id: Id) -> bool {
-        let index = id.index as usize;
-        if index >= self.slots.len() {
-            return false;
-        }
 
-        let item = &self.slots[index];
-        id.generation == item.generation
+    /// Gets the entry for `key`, which may be occupied, vacant, or stale.
+    ///
+    /// An id past the end of the arena is vacant unless it was trimmed off, and
+    /// filling it grows the arena up to its index; see [`VacantEntry::insert`](entry::VacantEntry::insert).
+    ///
+    /// # Panics
+    /// With the `arena-tags` feature, panics if `key` was handed out by a different arena.
+    pub fn entry(&mut self, key: K) -> entry::Entry<'_, T, K> {
+        entry::Entry::new(self, key)
     }
 
-    pub fn get_raw(&self, id: Id) -> Option<&T> {
-        let (index, generation) = (id.index() as usize, id.generation());
-        let slot = self.slots.get(index)?;
-        if generation != slot.generation {
-            return None;
+    /// Returns mutable references to several values at once.
+    ///
+    /// Fails if any key does not exist or if two keys refer to the same slot.
+    pub fn get_many_mut<const N: usize>(
+        &mut self,
+        keys: [K; N],
+    ) -> Result<[&mut T; N], GetManyError<K>> {
+        for (i, &key) in keys.iter().enumerate() {
+            let id = key.to_id();
+            if let Err(err) = self.access(id) {
+                return Err(GetManyError::Missing(key, err));
+            }
+            if keys[..i]
+                .iter()
+                .any(|other| other.to_id().index() == id.index())
+            {
+                return Err(GetManyError::Duplicate(key));
+            }
         }
 
-        let Entry::Present(item) = &slot.entry else {
-            return None;
-        };
-        Some(item)
+        // SAFETY: every key was just checked to exist and to be distinct.
+        Ok(unsafe { self.get_many_unchecked_mut(keys) })
     }
-    pub fn get_mut_raw(&mut self, id: Id) -> Option<&mut T> {
-        let (index, generation) = (id.index() as usize, id.generation());
-        let slot = self.slots.get_mut(index)?;
-        if generation != slot.generation {
-            return None;
-        }
-
-        let Entry::Present(item) = &mut slot.entry else {
-            return None;
-        };
-        Some(item)
+    /// Returns mutable references to several values at once without any checks.
+    ///
+    /// # Safety
+    /// Every key must exist in the arena and no two keys may share an index.
+    pub unsafe fn get_many_unchecked_mut<const N: usize>(&mut self, keys: [K; N]) -> [&mut T; N] {
+        let slots = self.slots.as_mut_ptr();
+        keys.map(|key| {
+            // SAFETY: the caller guarantees the indices are in bounds, present and disjoint.
+            let slot = unsafe { &mut *slots.add(key.to_id().slot()) };
+            let Entry::Present(item) = &mut slot.entry else {
+                unsafe { std::hint::unreachable_unchecked() }
+            };
+            item
+        })
     }
 
-    pub fn iter(&self) -> Iter<T> {
+    pub fn iter(&self) -> Iter<'_, T, K::Layout> {
         Iter {
-            length: self.len() as u32,
+            length: self.len(),
             returned: 0,
             slots: self.slots.iter(),
         }
     }
-    pub fn iter_mut(&mut self) -> IterMut<T> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, K::Layout> {
         IterMut {
-            length: self.len() as u32,
+            length: self.len(),
             returned: 0,
             slots: self.slots.iter_mut(),
         }
     }
+    pub fn iter_with_ids(&self) -> IterWithIds<'_, T, K> {
+        IterWithIds {
+            length: self.len(),
+            returned: 0,
+            tag: self.tag,
+            slots: self.slots.iter().enumerate(),
+        }
+    }
+    pub fn iter_mut_with_ids(&mut self) -> IterMutWithIds<'_, T, K> {
+        IterMutWithIds {
+            length: self.len(),
+            returned: 0,
+            tag: self.tag,
+            slots: self.slots.iter_mut().enumerate(),
+        }
+    }
+    pub fn into_iter_with_ids(self) -> IntoIterWithIds<T, K> {
+        IntoIterWithIds {
+            length: self.len(),
+            returned: 0,
+            tag: self.tag,
+            slots: self.slots.into_iter().enumerate(),
+        }
+    }
 
-    fn free_index(&mut self) -> Id {
-        if self.free_count > 0 {
-            let index = self.first_free;
-            let item = &self.slots[index as usize];
-            let generation = item.generation;
-            let Entry::Free { next_free } = item.entry else {
+    /// Keeps only the values for which `f` returns `true`.
+    #[track_caller]
+    pub fn retain<F: FnMut(K, &mut T) -> bool>(&mut self, mut f: F) {
+        for index in 0..self.slots.len() {
+            let slot = &mut self.slots[index];
+            let key = K::from_id(Id::new(index, slot.generation, self.tag));
+            if let Entry::Present(item) = &mut slot.entry {
+                if !f(key, item) {
+                    self.vacate(index);
+                }
+            }
+        }
+    }
+    /// Removes every value, yielding it together with its former key.
+    ///
+    /// If the iterator is dropped early, the remaining values are removed anyway.
+    pub fn drain(&mut self) -> Drain<'_, T, K> {
+        Drain {
+            length: self.len(),
+            returned: 0,
+            index: 0,
+            arena: self,
+        }
+    }
+    /// Lazily removes and yields the values for which `f` returns `true`.
+    ///
+    /// If the iterator is dropped early, the values not yet visited are kept.
+    pub fn extract_if<F: FnMut(K, &mut T) -> bool>(&mut self, f: F) -> ExtractIf<'_, T, F, K> {
+        ExtractIf {
+            arena: self,
+            index: 0,
+            pred: f,
+        }
+    }
+
+    #[track_caller]
+    fn vacate(&mut self, index: usize) -> T {
+        let slot = &mut self.slots[index];
+        if self.overflow_policy.retires::<K::Layout>(slot.generation) {
+            let Entry::Present(old) = std::mem::replace(&mut slot.entry, Entry::Retired) else {
                 unreachable!()
             };
-            self.first_free = next_free;
-            self.free_count -= 1;
-            Id { index, generation }
+            self.retired_count += 1;
+            return old;
+        }
+        slot.generation = layout::next_generation::<K::Layout>(slot.generation);
+
+        if self.quarantine_len > 0 {
+            let Entry::Present(old) = std::mem::replace(&mut slot.entry, Entry::quarantined())
+            else {
+                unreachable!()
+            };
+            self.quarantine(index);
+            return old;
+        }
+        let Some(old) = slot.entry.take(Integer::ZERO) else {
+            unreachable!()
+        };
+        self.push_free(index);
+
+        old
+    }
+    fn lookup(&self, id: Id<K::Layout>) -> Result<&Slot<T, K::Layout>, LookupError> {
+        if id.tag != self.tag {
+            return Err(LookupError::ForeignArena);
+        }
+        let Some(slot) = self.slots.get(id.slot()) else {
+            // The slot may have been trimmed off the end, see `trim_free_slots`.
+            if id.generation() < self.generation_watermark {
+                return Err(LookupError::Stale {
+                    current_generation: self.generation_watermark.to_u64(),
+                });
+            }
+            return Err(LookupError::OutOfBounds);
+        };
+        if id.generation() != slot.generation {
+            return Err(LookupError::Stale {
+                current_generation: slot.generation.to_u64(),
+            });
+        }
+        match slot.entry {
+            Entry::Present(_) => Ok(slot),
+            Entry::Free { .. } | Entry::Quarantined { .. } | Entry::Retired => {
+                Err(LookupError::Vacant)
+            }
+        }
+    }
+    /// Grows the free heap and the quarantine so that freeing any slot the
+    /// arena has capacity for does not allocate.
+    fn reserve_free_lists(&mut self) {
+        let (heap, quarantine) = self.free_list_shortfall();
+        self.free_heap.reserve(heap);
+        self.quarantine.reserve(quarantine);
+    }
+    fn try_reserve_free_lists(&mut self) -> Result<(), TryReserveError> {
+        let (heap, quarantine) = self.free_list_shortfall();
+        self.free_heap.try_reserve(heap)?;
+        self.quarantine.try_reserve(quarantine)
+    }
+    /// How many more entries the free heap and the quarantine need room for.
+    fn free_list_shortfall(&self) -> (usize, usize) {
+        let capacity = self.slots.capacity();
+        let heap = match self.free_list_policy {
+            FreeListPolicy::LowestIndex => capacity - self.free_heap.len(),
+            FreeListPolicy::Lifo | FreeListPolicy::Fifo => 0,
+        };
+        // The quarantine briefly holds one slot more than its length.
+        let quarantine = match self.quarantine_len {
+            0 => 0,
+            len => capacity.min(len.saturating_add(1)),
+        };
+        (heap, quarantine.saturating_sub(self.quarantine.len()))
+    }
+    /// Truncates the free slots at the end, leaving at least `min_len` slots,
+    /// and raises the generation watermark past them. Does not fix the free list.
+    fn trim_free_slots(&mut self, min_len: usize) {
+        let mut len = self.slots.len();
+        while len > min_len {
+            let slot = &self.slots[len - 1];
+            if !matches!(slot.entry, Entry::Free { .. }) {
+                break;
+            }
+            self.generation_watermark = self.generation_watermark.max(slot.generation);
+            len -= 1;
+        }
+        self.slots.truncate(len);
+    }
+    /// Like `lookup`, but panics on a poisoned quarantined slot.
+    fn access(&self, id: Id<K::Layout>) -> Result<&Slot<T, K::Layout>, LookupError> {
+        #[cfg(debug_assertions)]
+        if self.poison_quarantine && id.tag == self.tag {
+            if let Some(Slot {
+                entry: Entry::Quarantined { removed_at },
+                ..
+            }) = self.slots.get(id.slot())
+            {
+                panic!("Index {id} was used after its value was removed at {removed_at}");
+            }
+        }
+        self.lookup(id)
+    }
+    /// Whether one more value can be inserted.
+    fn check_room(&self) -> Result<(), CapacityError> {
+        if self.max_len.is_some_and(|max_len| self.len() >= max_len) {
+            return Err(CapacityError::MaxLenReached);
+        }
+        if self.free_count == 0 && self.slots.len() > K::Layout::MAX_INDEX.to_usize() {
+            return Err(CapacityError::IndexSpaceExhausted);
+        }
+        Ok(())
+    }
+    fn next_id(&self) -> Id<K::Layout> {
+        if let Some(index) = self.peek_free() {
+            Id::new(index, self.slots[index].generation, self.tag)
+        } else {
+            Id::new(self.slots.len(), self.generation_watermark, self.tag)
+        }
+    }
+    fn free_index(&mut self) -> Id<K::Layout> {
+        if let Some(index) = self.pop_free() {
+            Id::new(index, self.slots[index].generation, self.tag)
         } else {
             let index = self.slots.len();
             self.slots.push(Slot {
-                generation: 0,
-                entry: Entry::Free { next_free: 0 },
+                generation: self.generation_watermark,
+                entry: Entry::Free {
+                    next_free: Integer::ZERO,
+                },
             });
-            Id {
-                index: index as u32,
-                generation: 0,
-            }
+            Id::new(index, self.generation_watermark, self.tag)
         }
     }
 }
-impl<T> Default for Arena<T> {
+/// With the `arena-tags` feature the clone is a separate arena: it draws a new
+/// tag, so keys handed out by the original are rejected by the clone and vice versa.
+impl<T: Clone, K: Key<T>> Clone for Arena<T, K> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+            first_free: self.first_free,
+            last_free: self.last_free,
+            free_heap: self.free_heap.clone(),
+            free_count: self.free_count,
+            free_list_policy: self.free_list_policy,
+            quarantine: self.quarantine.clone(),
+            quarantine_len: self.quarantine_len,
+            poison_quarantine: self.poison_quarantine,
+            retired_count: self.retired_count,
+            generation_watermark: self.generation_watermark,
+            max_len: self.max_len,
+            overflow_policy: self.overflow_policy,
+            tag: ArenaTag::new(),
+            _key: PhantomData,
+        }
+    }
+}
+impl<T: Debug, K: Key<T>> Debug for Arena<T, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut f = f.debug_struct("Arena");
+        f.field("slots", &self.slots)
+            .field("first_free", &self.first_free)
+            .field("free_count", &self.free_count)
+            .field("free_list_policy", &self.free_list_policy)
+            .field("quarantine", &self.quarantine)
+            .field("quarantine_len", &self.quarantine_len)
+            .field("retired_count", &self.retired_count)
+            .field("overflow_policy", &self.overflow_policy);
+        #[cfg(feature = "arena-tags")]
+        f.field("tag", &self.tag);
+        f.finish()
+    }
+}
+impl<T, K: Key<T>> Default for Arena<T, K> {
     fn default() -> Self {
         Self::new()
     }
 }
-impl<T> IntoIterator for Arena<T> {
+impl<T, K: Key<T>> IntoIterator for Arena<T, K> {
     type Item = T;
-    type IntoIter = IntoIter<T>;
+    type IntoIter = IntoIter<T, K::Layout>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIter {
-            length: self.len() as u32,
+            length: self.len(),
             returned: 0,
             slots: self.slots.into_iter(),
         }
     }
 }
-impl<'a, T> IntoIterator for &'a Arena<T> {
-    type IntoIter = Iter<'a, T>;
+impl<'a, T, K: Key<T>> IntoIterator for &'a Arena<T, K> {
+    type IntoIter = Iter<'a, T, K::Layout>;
     type Item = &'a T;
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
     }
 }
-impl<'a, T> IntoIterator for &'a mut Arena<T> {
-    type IntoIter = IterMut<'a, T>;
+impl<'a, T, K: Key<T>> IntoIterator for &'a mut Arena<T, K> {
+    type IntoIter = IterMut<'a, T, K::Layout>;
     type Item = &'a mut T;
     fn into_iter(self) -> Self::IntoIter {
         self.iter_mut()
     }
 }
-impl<A> FromIterator<A> for Arena<A> {
+impl<A, K: Key<A>> FromIterator<A> for Arena<A, K> {
     fn from_iter<T: IntoIterator<Item = A>>(iter: T) -> Self {
-        let slots = iter
-            .into_iter()
-            .map(|a| Slot {
-                generation: 0,
-                entry: Entry::Present(a),
-            })
-            .collect();
-
-        Self {
-            slots,
-            first_free: 0,
-            free_count: 0,
-        }
+        let mut arena = Self::new();
+        arena.extend(iter);
+        arena
     }
 }
-impl<T> Index<Id> for Arena<T> {
+impl<T, K: Key<T>> Index<K> for Arena<T, K> {
     type Output = T;
-    fn index(&self, index: Id) -> &Self::Output {
-        match self.get_raw(index) {
-            Some(item) => item,
-            None => panic!("Index {index} does not exist in Arena"),
+    fn index(&self, key: K) -> &Self::Output {
+        match self.try_get(key) {
+            Ok(item) => item,
+            Err(err) => panic!("Index {} does not exist in Arena: {err}", key.to_id()),
         }
     }
 }
-impl<T> IndexMut<Id> for Arena<T> {
-    fn index_mut(&mut self, index: Id) -> &mut Self::Output {
-        match self.get_mut_raw(index) {
-            Some(item) => item,
-            None => panic!("Index {index} does not exist in Arena"),
+impl<T, K: Key<T>> IndexMut<K> for Arena<T, K> {
+    fn index_mut(&mut self, key: K) -> &mut Self::Output {
+        match self.try_get_mut(key) {
+            Ok(item) => item,
+            Err(err) => panic!("Index {} does not exist in Arena: {err}", key.to_id()),
         }
     }
 }
-impl<T, I> Index<I> for Arena<T>
-where
-    I: GenIndex<Item = T>,
-{
-    type Output = T;
-    fn index(&self, index: I) -> &Self::Output {
-        self.index(index.get_id())
-    }
-}
-impl<T, I> IndexMut<I> for Arena<T>
-where
-    I: GenIndex<Item = T>,
-{
-    fn index_mut(&mut self, index: I) -> &mut Self::Output {
-        self.index_mut(index.get_id())
-    }
-}
 
-impl<T> Extend<T> for Arena<T> {
+impl<T, K: Key<T>> Extend<T> for Arena<T, K> {
     fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
         for item in iter {
-            self.insert_raw(item);
+            self.insert(item);
         }
     }
 }
 
 #[derive(Clone, Debug)]
-struct Slot<T> {
-    generation: u32,
-    entry: Entry<T>,
+struct Slot<T, L: KeyLayout> {
+    generation: L::Generation,
+    entry: Entry<T, L::Index>,
 }
 
 #[derive(Clone, Debug)]
-enum Entry<T> {
+enum Entry<T, N> {
     Present(T),
-    Free { next_free: u32 },
+    Free {
+        next_free: N,
+    },
+    /// Freed, but held back from reuse until it leaves the quarantine.
+    Quarantined {
+        #[cfg(debug_assertions)]
+        removed_at: &'static std::panic::Location<'static>,
+    },
+    /// The generation ran out, so the slot is never handed out again.
+    Retired,
 }
-impl<T> Entry<T> {
-    fn take(&mut self, next_free: u32) -> Option<T> {
+impl<T, N> Entry<T, N> {
+    #[track_caller]
+    fn quarantined() -> Self {
+        Entry::Quarantined {
+            #[cfg(debug_assertions)]
+            removed_at: std::panic::Location::caller(),
+        }
+    }
+    fn take(&mut self, next_free: N) -> Option<T> {
         let old = std::mem::replace(self, Entry::Free { next_free });
         let Entry::Present(t) = old else { return None };
         Some(t)
     }
 }
 
+/// A handle to a value in an [`Arena`], laid out according to `L`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Id<L: KeyLayout = U32U32> {
+    repr: L::Repr,
+    tag: ArenaTag,
+}
+impl<L: KeyLayout> Id<L> {
+    fn new(index: usize, generation: L::Generation, tag: ArenaTag) -> Self {
+        Self {
+            repr: L::pack(L::Index::from_usize(index), generation),
+            tag,
+        }
+    }
+    fn slot(self) -> usize {
+        self.index().to_usize()
+    }
+    fn with_tag(self, tag: ArenaTag) -> Self {
+        Self { tag, ..self }
+    }
+    pub fn index(self) -> L::Index {
+        L::index(self.repr)
+    }
+    pub fn generation(self) -> L::Generation {
+        L::generation(self.repr)
+    }
+}
+impl<L: KeyLayout> Debug for Id<L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut f = f.debug_struct("Id");
+        f.field("index", &self.index())
+            .field("generation", &self.generation());
+        #[cfg(feature = "arena-tags")]
+        f.field("arena", &self.tag);
+        f.finish()
+    }
+}
+impl<L: KeyLayout> Display for Id<L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Id({}, {})", self.index(), self.generation())
+    }
+}
+
+/// What to do with a slot whose generation cannot be incremented any further.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OverflowPolicy {
+    /// Never hand the slot out again, so no old id can ever alias a new value.
+    #[default]
+    Retire,
+    /// Start over at generation zero, which lets very old ids become valid again.
+    Wrap,
+}
+impl OverflowPolicy {
+    fn retires<L: KeyLayout>(self, generation: L::Generation) -> bool {
+        self == Self::Retire && generation == L::MAX_GENERATION
+    }
+}
+
+/// Why an [`Id`] failed to resolve to a value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LookupError {
+    /// The index lies past the end of the arena.
+    OutOfBounds,
+    /// The slot holds no value.
+    Vacant,
+    /// The slot has been reused or freed since the id was handed out, or
+    /// trimmed off the end of the arena.
+    Stale { current_generation: u64 },
+    /// The id was handed out by a different arena.
+    ///
+    /// Only detected with the `arena-tags` feature.
+    ForeignArena,
+}
+impl Display for LookupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutOfBounds => write!(f, "index is out of bounds"),
+            Self::Vacant => write!(f, "slot is vacant"),
+            Self::Stale { current_generation } => {
+                write!(f, "id is stale, slot is at generation {current_generation}")
+            }
+            Self::ForeignArena => write!(f, "id belongs to a different arena"),
+        }
+    }
+}
+impl Error for LookupError {}
+
+/// Why an [`Arena`] has no room for another value.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub struct Id {
-    index: u32,
-    generation: u32,
+pub enum CapacityError {
+    /// Every index the key layout can represent is in use.
+    IndexSpaceExhausted,
+    /// The arena holds as many values as [`Arena::set_max_len`] allows, or as
+    /// many as an [`ArrayArena`] has room for.
+    MaxLenReached,
+}
+impl Display for CapacityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IndexSpaceExhausted => write!(f, "index space is exhausted"),
+            Self::MaxLenReached => write!(f, "maximum length is reached"),
+        }
+    }
 }
-impl Id {
-    pub fn index(self) -> u32 {
-        self.index
+impl Error for CapacityError {}
+
+/// The value an [`Arena`] refused to insert, together with the reason.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct InsertError<T> {
+    kind: CapacityError,
+    value: T,
+}
+impl<T> InsertError<T> {
+    pub fn kind(&self) -> CapacityError {
+        self.kind
+    }
+    pub fn into_value(self) -> T {
+        self.value
     }
-    pub fn generation(self) -> u32 {
-        self.generation
+}
+impl<T> Debug for InsertError<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InsertError")
+            .field("kind", &self.kind)
+            .finish_non_exhaustive()
     }
 }
-impl Display for Id {
+impl<T> Display for InsertError<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Id({}, {})", self.index, self.generation)
+        write!(f, "cannot insert into Arena: {}", self.kind)
     }
 }
+impl<T> Error for InsertError<T> {}
 
-pub trait GenIndex: From<Id> {
+/// Why [`Arena::try_insert_with`] did not insert a value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum InsertWithError<E> {
+    /// The arena had no room, so the closure was not called.
+    Full(CapacityError),
+    /// The closure returned an error.
+    Failed(E),
+}
+impl<E: Display> Display for InsertWithError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Full(kind) => write!(f, "cannot insert into Arena: {kind}"),
+            Self::Failed(err) => write!(f, "{err}"),
+        }
+    }
+}
+impl<E: Debug + Display> Error for InsertWithError<E> {}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GetManyError<K = Id> {
+    Missing(K, LookupError),
+    Duplicate(K),
+}
+impl<K: Display> Display for GetManyError<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing(key, err) => write!(f, "Index {key} does not exist in Arena: {err}"),
+            Self::Duplicate(key) => write!(f, "Index {key} was requested more than once"),
+        }
+    }
+}
+impl<K: Debug + Display> Error for GetManyError<K> {}
+
+pub trait GenIndex: Copy + From<Id<Self::Layout>> {
     type Item;
-    fn get_id(&self) -> Id;
+    type Layout: KeyLayout;
+    fn get_id(&self) -> Id<Self::Layout>;
+}
+
+/// Converts between a key type and the [`Id`] it stands for.
+///
+/// Implemented for raw [`Id`]s of every layout and for every [`GenIndex`].
+pub trait AsId: Copy {
+    type Layout: KeyLayout;
+    fn from_id(id: Id<Self::Layout>) -> Self;
+    fn to_id(self) -> Id<Self::Layout>;
+}
+impl<L: KeyLayout> AsId for Id<L> {
+    type Layout = L;
+    fn from_id(id: Id<L>) -> Self {
+        id
+    }
+    fn to_id(self) -> Id<L> {
+        self
+    }
+}
+impl<I: GenIndex> AsId for I {
+    type Layout = I::Layout;
+    fn from_id(id: Id<I::Layout>) -> Self {
+        I::from(id)
+    }
+    fn to_id(self) -> Id<I::Layout> {
+        self.get_id()
+    }
+}
+
+/// A key that an [`Arena`] of `T` can hand out and be indexed by.
+///
+/// Implemented for raw [`Id`]s of every layout and for every [`GenIndex`]
+/// whose item is `T`.
+pub trait Key<T>: AsId {}
+impl<T, L: KeyLayout> Key<T> for Id<L> {}
+impl<T, I: GenIndex<Item = T>> Key<T> for I {}
+
+/// Names the layout of an [`Id`] type, for code that only knows the type itself.
+pub trait RawId {
+    type Layout: KeyLayout;
+}
+impl<L: KeyLayout> RawId for Id<L> {
+    type Layout = L;
 }
 
 #[derive(Clone, Debug)]
-pub struct Iter<'a, T> {
-    slots: std::slice::Iter<'a, Slot<T>>,
-    length: u32,
-    returned: u32,
+pub struct Iter<'a, T, L: KeyLayout = U32U32> {
+    slots: std::slice::Iter<'a, Slot<T, L>>,
+    length: usize,
+    returned: usize,
 }
-impl<'a, T> Iterator for Iter<'a, T> {
+impl<'a, T, L: KeyLayout> Iterator for Iter<'a, T, L> {
     type Item = &'a T;
     fn next(&mut self) -> Option<Self::Item> {
         loop {
@@ -313,11 +1008,11 @@ impl<'a, T> Iterator for Iter<'a, T> {
         }
     }
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let length = (self.length - self.returned) as usize;
+        let length = self.length - self.returned;
         (length, Some(length))
     }
 }
-impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+impl<'a, T, L: KeyLayout> DoubleEndedIterator for Iter<'a, T, L> {
     fn next_back(&mut self) -> Option<Self::Item> {
         loop {
             let slot = self.slots.next_back()?;
@@ -328,16 +1023,16 @@ impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
         }
     }
 }
-impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
-impl<'a, T> FusedIterator for Iter<'a, T> {}
+impl<'a, T, L: KeyLayout> ExactSizeIterator for Iter<'a, T, L> {}
+impl<'a, T, L: KeyLayout> FusedIterator for Iter<'a, T, L> {}
 
 #[derive(Debug)]
-pub struct IterMut<'a, T> {
-    slots: std::slice::IterMut<'a, Slot<T>>,
-    length: u32,
-    returned: u32,
+pub struct IterMut<'a, T, L: KeyLayout = U32U32> {
+    slots: std::slice::IterMut<'a, Slot<T, L>>,
+    length: usize,
+    returned: usize,
 }
-impl<'a, T> Iterator for IterMut<'a, T> {
+impl<'a, T, L: KeyLayout> Iterator for IterMut<'a, T, L> {
     type Item = &'a mut T;
     fn next(&mut self) -> Option<Self::Item> {
         loop {
@@ -349,11 +1044,11 @@ impl<'a, T> Iterator for IterMut<'a, T> {
         }
     }
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let length = (self.length - self.returned) as usize;
+        let length = self.length - self.returned;
         (length, Some(length))
     }
 }
-impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+impl<'a, T, L: KeyLayout> DoubleEndedIterator for IterMut<'a, T, L> {
     fn next_back(&mut self) -> Option<Self::Item> {
         loop {
             let slot = self.slots.next_back()?;
@@ -364,16 +1059,16 @@ impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
         }
     }
 }
-impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
-impl<'a, T> FusedIterator for IterMut<'a, T> {}
+impl<'a, T, L: KeyLayout> ExactSizeIterator for IterMut<'a, T, L> {}
+impl<'a, T, L: KeyLayout> FusedIterator for IterMut<'a, T, L> {}
 
 #[derive(Clone, Debug)]
-pub struct IntoIter<T> {
-    slots: std::vec::IntoIter<Slot<T>>,
-    length: u32,
-    returned: u32,
+pub struct IntoIter<T, L: KeyLayout = U32U32> {
+    slots: std::vec::IntoIter<Slot<T, L>>,
+    length: usize,
+    returned: usize,
 }
-impl<T> Iterator for IntoIter<T> {
+impl<T, L: KeyLayout> Iterator for IntoIter<T, L> {
     type Item = T;
     fn next(&mut self) -> Option<Self::Item> {
         loop {
@@ -385,11 +1080,11 @@ impl<T> Iterator for IntoIter<T> {
         }
     }
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let length = (self.length - self.returned) as usize;
+        let length = self.length - self.returned;
         (length, Some(length))
     }
 }
-impl<T> DoubleEndedIterator for IntoIter<T> {
+impl<T, L: KeyLayout> DoubleEndedIterator for IntoIter<T, L> {
     fn next_back(&mut self) -> Option<Self::Item> {
         loop {
             let slot = self.slots.next_back()?;
@@ -400,5 +1095,184 @@ impl<T> DoubleEndedIterator for IntoIter<T> {
         }
     }
 }
-impl<T> ExactSizeIterator for IntoIter<T> {}
-impl<T> FusedIterator for IntoIter<T> {}
+impl<T, L: KeyLayout> ExactSizeIterator for IntoIter<T, L> {}
+impl<T, L: KeyLayout> FusedIterator for IntoIter<T, L> {}
+
+#[derive(Clone, Debug)]
+pub struct IterWithIds<'a, T, K: Key<T> = Id> {
+    slots: std::iter::Enumerate<std::slice::Iter<'a, Slot<T, K::Layout>>>,
+    length: usize,
+    returned: usize,
+    tag: ArenaTag,
+}
+impl<'a, T, K: Key<T>> Iterator for IterWithIds<'a, T, K> {
+    type Item = (K, &'a T);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, slot) = self.slots.next()?;
+            if let Entry::Present(item) = &slot.entry {
+                self.returned += 1;
+                return Some((K::from_id(Id::new(index, slot.generation, self.tag)), item));
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.length - self.returned;
+        (length, Some(length))
+    }
+}
+impl<'a, T, K: Key<T>> DoubleEndedIterator for IterWithIds<'a, T, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, slot) = self.slots.next_back()?;
+            if let Entry::Present(item) = &slot.entry {
+                self.returned += 1;
+                return Some((K::from_id(Id::new(index, slot.generation, self.tag)), item));
+            }
+        }
+    }
+}
+impl<'a, T, K: Key<T>> ExactSizeIterator for IterWithIds<'a, T, K> {}
+impl<'a, T, K: Key<T>> FusedIterator for IterWithIds<'a, T, K> {}
+
+#[derive(Debug)]
+pub struct IterMutWithIds<'a, T, K: Key<T> = Id> {
+    slots: std::iter::Enumerate<std::slice::IterMut<'a, Slot<T, K::Layout>>>,
+    length: usize,
+    returned: usize,
+    tag: ArenaTag,
+}
+impl<'a, T, K: Key<T>> Iterator for IterMutWithIds<'a, T, K> {
+    type Item = (K, &'a mut T);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, slot) = self.slots.next()?;
+            if let Entry::Present(item) = &mut slot.entry {
+                self.returned += 1;
+                return Some((K::from_id(Id::new(index, slot.generation, self.tag)), item));
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.length - self.returned;
+        (length, Some(length))
+    }
+}
+impl<'a, T, K: Key<T>> DoubleEndedIterator for IterMutWithIds<'a, T, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, slot) = self.slots.next_back()?;
+            if let Entry::Present(item) = &mut slot.entry {
+                self.returned += 1;
+                return Some((K::from_id(Id::new(index, slot.generation, self.tag)), item));
+            }
+        }
+    }
+}
+impl<'a, T, K: Key<T>> ExactSizeIterator for IterMutWithIds<'a, T, K> {}
+impl<'a, T, K: Key<T>> FusedIterator for IterMutWithIds<'a, T, K> {}
+
+#[derive(Clone, Debug)]
+pub struct IntoIterWithIds<T, K: Key<T> = Id> {
+    slots: std::iter::Enumerate<std::vec::IntoIter<Slot<T, K::Layout>>>,
+    length: usize,
+    returned: usize,
+    tag: ArenaTag,
+}
+impl<T, K: Key<T>> Iterator for IntoIterWithIds<T, K> {
+    type Item = (K, T);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, slot) = self.slots.next()?;
+            if let Entry::Present(item) = slot.entry {
+                self.returned += 1;
+                return Some((K::from_id(Id::new(index, slot.generation, self.tag)), item));
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.length - self.returned;
+        (length, Some(length))
+    }
+}
+impl<T, K: Key<T>> DoubleEndedIterator for IntoIterWithIds<T, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, slot) = self.slots.next_back()?;
+            if let Entry::Present(item) = slot.entry {
+                self.returned += 1;
+                return Some((K::from_id(Id::new(index, slot.generation, self.tag)), item));
+            }
+        }
+    }
+}
+impl<T, K: Key<T>> ExactSizeIterator for IntoIterWithIds<T, K> {}
+impl<T, K: Key<T>> FusedIterator for IntoIterWithIds<T, K> {}
+
+#[derive(Debug)]
+pub struct Drain<'a, T, K: Key<T> = Id> {
+    arena: &'a mut Arena<T, K>,
+    index: usize,
+    length: usize,
+    returned: usize,
+}
+impl<'a, T, K: Key<T>> Iterator for Drain<'a, T, K> {
+    type Item = (K, T);
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.arena.slots.len() {
+            let index = self.index;
+            self.index += 1;
+
+            let slot = &self.arena.slots[index];
+            if let Entry::Present(_) = slot.entry {
+                let key = K::from_id(Id::new(index, slot.generation, self.arena.tag));
+                self.returned += 1;
+                return Some((key, self.arena.vacate(index)));
+            }
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.length - self.returned;
+        (length, Some(length))
+    }
+}
+impl<'a, T, K: Key<T>> ExactSizeIterator for Drain<'a, T, K> {}
+impl<'a, T, K: Key<T>> FusedIterator for Drain<'a, T, K> {}
+impl<'a, T, K: Key<T>> Drop for Drain<'a, T, K> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+pub struct ExtractIf<'a, T, F, K: Key<T> = Id> {
+    arena: &'a mut Arena<T, K>,
+    index: usize,
+    pred: F,
+}
+impl<'a, T, F, K: Key<T>> Iterator for ExtractIf<'a, T, F, K>
+where
+    F: FnMut(K, &mut T) -> bool,
+{
+    type Item = (K, T);
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.arena.slots.len() {
+            let index = self.index;
+            self.index += 1;
+
+            let slot = &mut self.arena.slots[index];
+            let key = K::from_id(Id::new(index, slot.generation, self.arena.tag));
+            let Entry::Present(item) = &mut slot.entry else {
+                continue;
+            };
+            if (self.pred)(key, item) {
+                return Some((key, self.arena.vacate(index)));
+            }
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.arena.slots.len() - self.index))
+    }
+}
+impl<'a, T, F, K: Key<T>> FusedIterator for ExtractIf<'a, T, F, K> where F: FnMut(K, &mut T) -> bool {}
@@ -0,0 +1,83 @@
+/// Declares typed ids without the `derive` feature.
+///
+/// ```
+/// use gen_arena::{gen_index, Arena, Id, U16U16};
+///
+/// pub struct Unit;
+/// pub struct Packet(u8);
+///
+/// gen_index! {
+///     pub struct UnitId(Id) => Unit;
+///     pub struct PacketId(Id<U16U16>) => Packet;
+/// }
+///
+/// let mut units: Arena<Unit, UnitId> = Arena::new();
+/// let unit = units.insert(Unit);
+/// assert!(units.contains(unit));
+///
+/// let mut packets: Arena<Packet, PacketId> = Arena::new();
+/// let packet = packets.insert(Packet(7));
+/// assert_eq!(packets[packet].0, 7);
+/// assert_eq!(packet.to_string(), "PacketId(0, 0)");
+/// assert_eq!(std::mem::size_of::<Option<PacketId>>(), std::mem::size_of::<PacketId>());
+/// ```
+///
+/// Each struct gets the same impls as with `#[derive(GenIndex)]`.
+#[macro_export]
+macro_rules! gen_index {
+    ($($(#[$attr:meta])* $vis:vis struct $name:ident($field_vis:vis $id:ty) => $item:ty;)*) => {$(
+        $(#[$attr])*
+        $vis struct $name($field_vis $id);
+
+        impl ::core::convert::From<$id> for $name {
+            fn from(id: $id) -> Self {
+                Self(id)
+            }
+        }
+        impl ::core::convert::From<$name> for $id {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+        impl $crate::GenIndex for $name {
+            type Item = $item;
+            type Layout = <$id as $crate::RawId>::Layout;
+            fn get_id(&self) -> $id {
+                self.0
+            }
+        }
+        impl ::core::clone::Clone for $name {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+        impl ::core::marker::Copy for $name {}
+        impl ::core::cmp::PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+        impl ::core::cmp::Eq for $name {}
+        impl ::core::hash::Hash for $name {
+            fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+                ::core::hash::Hash::hash(&self.0, state)
+            }
+        }
+        impl ::core::fmt::Debug for $name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.debug_tuple(::core::stringify!($name)).field(&self.0).finish()
+            }
+        }
+        impl ::core::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                ::core::write!(
+                    f,
+                    "{}({}, {})",
+                    ::core::stringify!($name),
+                    self.0.index(),
+                    self.0.generation()
+                )
+            }
+        }
+    )*};
+}
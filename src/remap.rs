@@ -0,0 +1,117 @@
+use crate::{
+    layout, Arena, AsId, Entry, Id, IdSet, Integer, Key, SecondaryMap, SparseSecondaryMap,
+};
+
+/// Maps the keys of an [`Arena`] from before a [`Arena::compact`] to the keys
+/// the same values have afterwards.
+#[derive(Clone, Debug)]
+pub struct Remap<K: AsId> {
+    keys: SecondaryMap<K, K>,
+}
+impl<K: AsId> Remap<K> {
+    /// The new key for `old`, or `None` if `old` did not refer to a value.
+    pub fn get(&self, old: K) -> Option<K> {
+        self.keys.get(old).copied()
+    }
+    /// The number of values that were in the arena.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    /// Iterates over `(old, new)` pairs, including values that did not move.
+    pub fn iter(&self) -> impl Iterator<Item = (K, K)> + '_ {
+        self.keys.iter().map(|(old, &new)| (old, new))
+    }
+}
+
+/// Something that holds arena keys and can be rewritten after a [`Arena::compact`].
+///
+/// Implementations should drop whatever belonged to keys that map to nothing.
+pub trait Remappable<K: AsId> {
+    fn remap(&mut self, remap: &Remap<K>);
+}
+impl<K: AsId, V> Remappable<K> for SecondaryMap<K, V> {
+    fn remap(&mut self, remap: &Remap<K>) {
+        let old = std::mem::take(self);
+        self.extend(
+            old.into_iter()
+                .filter_map(|(key, value)| Some((remap.get(key)?, value))),
+        );
+    }
+}
+impl<K: AsId, V> Remappable<K> for SparseSecondaryMap<K, V> {
+    fn remap(&mut self, remap: &Remap<K>) {
+        let old = std::mem::take(self);
+        self.extend(
+            old.into_iter()
+                .filter_map(|(key, value)| Some((remap.get(key)?, value))),
+        );
+    }
+}
+impl<K: AsId> Remappable<K> for IdSet<K> {
+    fn remap(&mut self, remap: &Remap<K>) {
+        let old = std::mem::take(self);
+        self.extend(old.iter().filter_map(|key| remap.get(key)));
+    }
+}
+
+impl<T, K: Key<T>> Arena<T, K> {
+    /// Moves every value towards the front of the arena and releases the
+    /// memory of the slots left over at the end.
+    ///
+    /// Values keep their relative order. Moved values get new keys, which the
+    /// returned [`Remap`] translates to; old keys of moved values become stale
+    /// instead of resolving to a different value. Quarantined slots are
+    /// released, and retired slots stay where they are, as do any free slots
+    /// before the last retired one.
+    pub fn compact(&mut self) -> Remap<K> {
+        let mut keys = SecondaryMap::with_capacity(self.slots.len());
+        let mut dest = 0;
+        for src in 0..self.slots.len() {
+            if !matches!(self.slots[src].entry, Entry::Present(_)) {
+                continue;
+            }
+            while dest < src && matches!(self.slots[dest].entry, Entry::Retired) {
+                dest += 1;
+            }
+
+            let old = K::from_id(Id::new(src, self.slots[src].generation, self.tag));
+            if dest < src {
+                let slot = &mut self.slots[src];
+                let generation = slot.generation;
+                let entry = if self.overflow_policy.retires::<K::Layout>(generation) {
+                    self.retired_count += 1;
+                    Entry::Retired
+                } else {
+                    slot.generation = layout::next_generation::<K::Layout>(generation);
+                    Entry::Free {
+                        next_free: Integer::ZERO,
+                    }
+                };
+                let value = std::mem::replace(&mut slot.entry, entry);
+                self.slots[dest].entry = value;
+            }
+            keys.insert(
+                old,
+                K::from_id(Id::new(dest, self.slots[dest].generation, self.tag)),
+            );
+            dest += 1;
+        }
+
+        for slot in &mut self.slots[dest..] {
+            if let Entry::Quarantined { .. } = slot.entry {
+                slot.entry = Entry::Free {
+                    next_free: Integer::ZERO,
+                };
+            }
+        }
+        self.quarantine.clear();
+        self.trim_free_slots(dest);
+        self.rebuild_free_list();
+        self.slots.shrink_to_fit();
+
+        Remap { keys }
+    }
+}
@@ -0,0 +1,443 @@
+use crate::{AsId, Id, Integer};
+use std::{
+    fmt::Debug,
+    iter::FusedIterator,
+    marker::PhantomData,
+    ops::{Index, IndexMut},
+};
+
+/// Associates extra values with the keys of an [`Arena`](crate::Arena).
+///
+/// Values live in a vector indexed by [`Id::index`], next to the id they were
+/// inserted under. Once the primary slot is reused, the old key misses and
+/// inserting under the new key overwrites the stale value.
+pub struct SecondaryMap<K: AsId, V> {
+    slots: Vec<Option<(Id<K::Layout>, V)>>,
+    len: usize,
+    _key: PhantomData<fn() -> K>,
+}
+impl<K: AsId, V> SecondaryMap<K, V> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slots: Vec::with_capacity(capacity),
+            len: 0,
+            _key: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// The number of slots the map can cover without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.len = 0;
+    }
+
+    /// Associates `value` with `key`, returning the value previously stored
+    /// under the very same key.
+    ///
+    /// A value stored under an older generation of the slot is dropped. If the
+    /// slot already holds a value for a newer generation, `key` is stale and
+    /// `value` is dropped instead.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let id = key.to_id();
+        let index = id.index().to_usize();
+        if self.slots.len() <= index {
+            self.slots.resize_with(index + 1, || None);
+        }
+
+        let slot = &mut self.slots[index];
+        match slot {
+            Some((old, _)) if old.generation() > id.generation() => None,
+            Some((old, item)) if *old == id => Some(std::mem::replace(item, value)),
+            Some(_) => {
+                *slot = Some((id, value));
+                None
+            }
+            None => {
+                *slot = Some((id, value));
+                self.len += 1;
+                None
+            }
+        }
+    }
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        let id = key.to_id();
+        let slot = self.slots.get_mut(id.index().to_usize())?;
+        if !matches!(slot, Some((old, _)) if *old == id) {
+            return None;
+        }
+        self.len -= 1;
+        slot.take().map(|(_, value)| value)
+    }
+    pub fn contains(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: K) -> Option<&V> {
+        let id = key.to_id();
+        match self.slots.get(id.index().to_usize())? {
+            Some((old, value)) if *old == id => Some(value),
+            _ => None,
+        }
+    }
+    pub fn get_mut(&mut self, key: K) -> Option<&mut V> {
+        let id = key.to_id();
+        match self.slots.get_mut(id.index().to_usize())? {
+            Some((old, value)) if *old == id => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns `None` if `key` is stale, i.e. the slot holds a value for a newer generation.
+    pub fn entry(&mut self, key: K) -> Option<Entry<'_, K, V>> {
+        let id = key.to_id();
+        let index = id.index().to_usize();
+
+        match self.slots.get(index).unwrap_or(&None) {
+            Some((old, _)) if old.generation() > id.generation() => None,
+            Some((old, _)) if *old == id => Some(Entry::Occupied(OccupiedEntry {
+                map: self,
+                key,
+                index,
+            })),
+            _ => Some(Entry::Vacant(VacantEntry {
+                map: self,
+                key,
+                index,
+            })),
+        }
+    }
+
+    /// Keeps only the values for which `f` returns `true`.
+    pub fn retain<F: FnMut(K, &mut V) -> bool>(&mut self, mut f: F) {
+        for slot in &mut self.slots {
+            if let Some((id, value)) = slot {
+                if !f(K::from_id(*id), value) {
+                    *slot = None;
+                    self.len -= 1;
+                }
+            }
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            slots: self.slots.iter(),
+            length: self.len,
+            returned: 0,
+            _key: PhantomData,
+        }
+    }
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            slots: self.slots.iter_mut(),
+            length: self.len,
+            returned: 0,
+            _key: PhantomData,
+        }
+    }
+}
+impl<K: AsId, V: Clone> Clone for SecondaryMap<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+            len: self.len,
+            _key: PhantomData,
+        }
+    }
+}
+impl<K: AsId, V: Debug> Debug for SecondaryMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.slots.iter().flatten().map(|(id, value)| (id, value)))
+            .finish()
+    }
+}
+impl<K: AsId, V> Default for SecondaryMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<K: AsId, V> IntoIterator for SecondaryMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            slots: self.slots.into_iter(),
+            length: self.len,
+            returned: 0,
+            _key: PhantomData,
+        }
+    }
+}
+impl<'a, K: AsId, V> IntoIterator for &'a SecondaryMap<K, V> {
+    type IntoIter = Iter<'a, K, V>;
+    type Item = (K, &'a V);
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl<'a, K: AsId, V> IntoIterator for &'a mut SecondaryMap<K, V> {
+    type IntoIter = IterMut<'a, K, V>;
+    type Item = (K, &'a mut V);
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+impl<K: AsId, V> FromIterator<(K, V)> for SecondaryMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+impl<K: AsId, V> Extend<(K, V)> for SecondaryMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+impl<K: AsId, V> Index<K> for SecondaryMap<K, V> {
+    type Output = V;
+    fn index(&self, key: K) -> &Self::Output {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("Index {} does not exist in SecondaryMap", key.to_id()),
+        }
+    }
+}
+impl<K: AsId, V> IndexMut<K> for SecondaryMap<K, V> {
+    fn index_mut(&mut self, key: K) -> &mut Self::Output {
+        match self.get_mut(key) {
+            Some(value) => value,
+            None => panic!("Index {} does not exist in SecondaryMap", key.to_id()),
+        }
+    }
+}
+
+/// A view into a single slot of a [`SecondaryMap`], obtained from [`SecondaryMap::entry`].
+pub enum Entry<'a, K: AsId, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+impl<'a, K: AsId, V> Entry<'a, K, V> {
+    pub fn key(&self) -> K {
+        match self {
+            Self::Occupied(entry) => entry.key(),
+            Self::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Self::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Self::Occupied(entry) => entry.into_mut(),
+            Self::Vacant(entry) => entry.insert(default()),
+        }
+    }
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+pub struct OccupiedEntry<'a, K: AsId, V> {
+    map: &'a mut SecondaryMap<K, V>,
+    key: K,
+    index: usize,
+}
+impl<'a, K: AsId, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> K {
+        self.key
+    }
+
+    pub fn get(&self) -> &V {
+        let Some((_, value)) = &self.map.slots[self.index] else {
+            unreachable!()
+        };
+        value
+    }
+    pub fn get_mut(&mut self) -> &mut V {
+        let Some((_, value)) = &mut self.map.slots[self.index] else {
+            unreachable!()
+        };
+        value
+    }
+    pub fn into_mut(self) -> &'a mut V {
+        let Some((_, value)) = &mut self.map.slots[self.index] else {
+            unreachable!()
+        };
+        value
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+    pub fn remove(self) -> V {
+        self.map.len -= 1;
+        let Some((_, value)) = self.map.slots[self.index].take() else {
+            unreachable!()
+        };
+        value
+    }
+}
+
+pub struct VacantEntry<'a, K: AsId, V> {
+    map: &'a mut SecondaryMap<K, V>,
+    key: K,
+    index: usize,
+}
+impl<'a, K: AsId, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> K {
+        self.key
+    }
+
+    /// Inserts `value`, dropping any value stored under an older generation of the slot.
+    pub fn insert(self, value: V) -> &'a mut V {
+        if self.map.slots.len() <= self.index {
+            self.map.slots.resize_with(self.index + 1, || None);
+        }
+        let slot = &mut self.map.slots[self.index];
+        if slot.is_none() {
+            self.map.len += 1;
+        }
+        let (_, value) = slot.insert((self.key.to_id(), value));
+        value
+    }
+}
+
+pub struct Iter<'a, K: AsId, V> {
+    slots: std::slice::Iter<'a, Option<(Id<K::Layout>, V)>>,
+    length: usize,
+    returned: usize,
+    _key: PhantomData<fn() -> K>,
+}
+impl<'a, K: AsId, V> Iterator for Iter<'a, K, V> {
+    type Item = (K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((id, value)) = self.slots.next()? {
+                self.returned += 1;
+                return Some((K::from_id(*id), value));
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.length - self.returned;
+        (length, Some(length))
+    }
+}
+impl<'a, K: AsId, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((id, value)) = self.slots.next_back()? {
+                self.returned += 1;
+                return Some((K::from_id(*id), value));
+            }
+        }
+    }
+}
+impl<'a, K: AsId, V> ExactSizeIterator for Iter<'a, K, V> {}
+impl<'a, K: AsId, V> FusedIterator for Iter<'a, K, V> {}
+impl<'a, K: AsId, V> Clone for Iter<'a, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+            length: self.length,
+            returned: self.returned,
+            _key: PhantomData,
+        }
+    }
+}
+
+pub struct IterMut<'a, K: AsId, V> {
+    slots: std::slice::IterMut<'a, Option<(Id<K::Layout>, V)>>,
+    length: usize,
+    returned: usize,
+    _key: PhantomData<fn() -> K>,
+}
+impl<'a, K: AsId, V> Iterator for IterMut<'a, K, V> {
+    type Item = (K, &'a mut V);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((id, value)) = self.slots.next()? {
+                self.returned += 1;
+                return Some((K::from_id(*id), value));
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.length - self.returned;
+        (length, Some(length))
+    }
+}
+impl<'a, K: AsId, V> DoubleEndedIterator for IterMut<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((id, value)) = self.slots.next_back()? {
+                self.returned += 1;
+                return Some((K::from_id(*id), value));
+            }
+        }
+    }
+}
+impl<'a, K: AsId, V> ExactSizeIterator for IterMut<'a, K, V> {}
+impl<'a, K: AsId, V> FusedIterator for IterMut<'a, K, V> {}
+
+pub struct IntoIter<K: AsId, V> {
+    slots: std::vec::IntoIter<Option<(Id<K::Layout>, V)>>,
+    length: usize,
+    returned: usize,
+    _key: PhantomData<fn() -> K>,
+}
+impl<K: AsId, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((id, value)) = self.slots.next()? {
+                self.returned += 1;
+                return Some((K::from_id(id), value));
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let length = self.length - self.returned;
+        (length, Some(length))
+    }
+}
+impl<K: AsId, V> DoubleEndedIterator for IntoIter<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((id, value)) = self.slots.next_back()? {
+                self.returned += 1;
+                return Some((K::from_id(id), value));
+            }
+        }
+    }
+}
+impl<K: AsId, V> ExactSizeIterator for IntoIter<K, V> {}
+impl<K: AsId, V> FusedIterator for IntoIter<K, V> {}
@@ -0,0 +1,358 @@
+use crate::{AsId, Id, KeyLayout};
+use std::{
+    collections::{hash_map, HashMap},
+    fmt::Debug,
+    iter::FusedIterator,
+    marker::PhantomData,
+    ops::{Index, IndexMut},
+};
+
+type SlotIndex<K> = <<K as AsId>::Layout as KeyLayout>::Index;
+type Slot<K, V> = (Id<<K as AsId>::Layout>, V);
+type Slots<K, V> = HashMap<SlotIndex<K>, Slot<K, V>>;
+
+/// Like [`SecondaryMap`](crate::SecondaryMap), but backed by a hash map.
+///
+/// Memory grows with the number of values rather than with the largest index,
+/// which suits values that are only attached to a few keys.
+pub struct SparseSecondaryMap<K: AsId, V> {
+    slots: Slots<K, V>,
+    _key: PhantomData<fn() -> K>,
+}
+impl<K: AsId, V> SparseSecondaryMap<K, V> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slots: HashMap::with_capacity(capacity),
+            _key: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    /// Associates `value` with `key`, returning the value previously stored
+    /// under the very same key.
+    ///
+    /// A value stored under an older generation of the slot is dropped. If the
+    /// slot already holds a value for a newer generation, `key` is stale and
+    /// `value` is dropped instead.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let id = key.to_id();
+        match self.slots.entry(id.index()) {
+            hash_map::Entry::Occupied(mut entry) => {
+                let (old, item) = entry.get_mut();
+                if old.generation() > id.generation() {
+                    None
+                } else if *old == id {
+                    Some(std::mem::replace(item, value))
+                } else {
+                    entry.insert((id, value));
+                    None
+                }
+            }
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert((id, value));
+                None
+            }
+        }
+    }
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        let id = key.to_id();
+        match self.slots.entry(id.index()) {
+            hash_map::Entry::Occupied(entry) if entry.get().0 == id => Some(entry.remove().1),
+            _ => None,
+        }
+    }
+    pub fn contains(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn get(&self, key: K) -> Option<&V> {
+        let id = key.to_id();
+        match self.slots.get(&id.index())? {
+            (old, value) if *old == id => Some(value),
+            _ => None,
+        }
+    }
+    pub fn get_mut(&mut self, key: K) -> Option<&mut V> {
+        let id = key.to_id();
+        match self.slots.get_mut(&id.index())? {
+            (old, value) if *old == id => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns `None` if `key` is stale, i.e. the slot holds a value for a newer generation.
+    pub fn entry(&mut self, key: K) -> Option<Entry<'_, K, V>> {
+        let id = key.to_id();
+        match self.slots.entry(id.index()) {
+            hash_map::Entry::Occupied(entry) => {
+                let old = entry.get().0;
+                if old.generation() > id.generation() {
+                    None
+                } else if old == id {
+                    Some(Entry::Occupied(OccupiedEntry { inner: entry, key }))
+                } else {
+                    Some(Entry::Vacant(VacantEntry {
+                        inner: hash_map::Entry::Occupied(entry),
+                        key,
+                    }))
+                }
+            }
+            entry => Some(Entry::Vacant(VacantEntry { inner: entry, key })),
+        }
+    }
+
+    /// Keeps only the values for which `f` returns `true`.
+    pub fn retain<F: FnMut(K, &mut V) -> bool>(&mut self, mut f: F) {
+        self.slots
+            .retain(|_, (id, value)| f(K::from_id(*id), value));
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            slots: self.slots.values(),
+            _key: PhantomData,
+        }
+    }
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            slots: self.slots.values_mut(),
+            _key: PhantomData,
+        }
+    }
+}
+impl<K: AsId, V: Clone> Clone for SparseSecondaryMap<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+            _key: PhantomData,
+        }
+    }
+}
+impl<K: AsId, V: Debug> Debug for SparseSecondaryMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.slots.values().map(|(id, value)| (id, value)))
+            .finish()
+    }
+}
+impl<K: AsId, V> Default for SparseSecondaryMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<K: AsId, V> IntoIterator for SparseSecondaryMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            slots: self.slots.into_values(),
+            _key: PhantomData,
+        }
+    }
+}
+impl<'a, K: AsId, V> IntoIterator for &'a SparseSecondaryMap<K, V> {
+    type IntoIter = Iter<'a, K, V>;
+    type Item = (K, &'a V);
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+impl<'a, K: AsId, V> IntoIterator for &'a mut SparseSecondaryMap<K, V> {
+    type IntoIter = IterMut<'a, K, V>;
+    type Item = (K, &'a mut V);
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+impl<K: AsId, V> FromIterator<(K, V)> for SparseSecondaryMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+impl<K: AsId, V> Extend<(K, V)> for SparseSecondaryMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+impl<K: AsId, V> Index<K> for SparseSecondaryMap<K, V> {
+    type Output = V;
+    fn index(&self, key: K) -> &Self::Output {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("Index {} does not exist in SparseSecondaryMap", key.to_id()),
+        }
+    }
+}
+impl<K: AsId, V> IndexMut<K> for SparseSecondaryMap<K, V> {
+    fn index_mut(&mut self, key: K) -> &mut Self::Output {
+        match self.get_mut(key) {
+            Some(value) => value,
+            None => panic!("Index {} does not exist in SparseSecondaryMap", key.to_id()),
+        }
+    }
+}
+
+/// A view into a single slot of a [`SparseSecondaryMap`], obtained from
+/// [`SparseSecondaryMap::entry`].
+pub enum Entry<'a, K: AsId, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+impl<'a, K: AsId, V> Entry<'a, K, V> {
+    pub fn key(&self) -> K {
+        match self {
+            Self::Occupied(entry) => entry.key(),
+            Self::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Self::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Self::Occupied(entry) => entry.into_mut(),
+            Self::Vacant(entry) => entry.insert(default()),
+        }
+    }
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+pub struct OccupiedEntry<'a, K: AsId, V> {
+    inner: hash_map::OccupiedEntry<'a, SlotIndex<K>, Slot<K, V>>,
+    key: K,
+}
+impl<'a, K: AsId, V> OccupiedEntry<'a, K, V> {
+    pub fn key(&self) -> K {
+        self.key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.inner.get().1
+    }
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.inner.get_mut().1
+    }
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.inner.into_mut().1
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+    pub fn remove(self) -> V {
+        self.inner.remove().1
+    }
+}
+
+pub struct VacantEntry<'a, K: AsId, V> {
+    /// Occupied if the slot holds a value for an older generation.
+    inner: hash_map::Entry<'a, SlotIndex<K>, Slot<K, V>>,
+    key: K,
+}
+impl<'a, K: AsId, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> K {
+        self.key
+    }
+
+    /// Inserts `value`, dropping any value stored under an older generation of the slot.
+    pub fn insert(self, value: V) -> &'a mut V {
+        let slot = (self.key.to_id(), value);
+        let (_, value) = match self.inner {
+            hash_map::Entry::Occupied(mut entry) => {
+                entry.insert(slot);
+                entry.into_mut()
+            }
+            hash_map::Entry::Vacant(entry) => entry.insert(slot),
+        };
+        value
+    }
+}
+
+pub struct Iter<'a, K: AsId, V> {
+    slots: hash_map::Values<'a, SlotIndex<K>, Slot<K, V>>,
+    _key: PhantomData<fn() -> K>,
+}
+impl<'a, K: AsId, V> Iterator for Iter<'a, K, V> {
+    type Item = (K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, value) = self.slots.next()?;
+        Some((K::from_id(*id), value))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slots.size_hint()
+    }
+}
+impl<'a, K: AsId, V> ExactSizeIterator for Iter<'a, K, V> {}
+impl<'a, K: AsId, V> FusedIterator for Iter<'a, K, V> {}
+impl<'a, K: AsId, V> Clone for Iter<'a, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+            _key: PhantomData,
+        }
+    }
+}
+
+pub struct IterMut<'a, K: AsId, V> {
+    slots: hash_map::ValuesMut<'a, SlotIndex<K>, Slot<K, V>>,
+    _key: PhantomData<fn() -> K>,
+}
+impl<'a, K: AsId, V> Iterator for IterMut<'a, K, V> {
+    type Item = (K, &'a mut V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, value) = self.slots.next()?;
+        Some((K::from_id(*id), value))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slots.size_hint()
+    }
+}
+impl<'a, K: AsId, V> ExactSizeIterator for IterMut<'a, K, V> {}
+impl<'a, K: AsId, V> FusedIterator for IterMut<'a, K, V> {}
+
+pub struct IntoIter<K: AsId, V> {
+    slots: hash_map::IntoValues<SlotIndex<K>, Slot<K, V>>,
+    _key: PhantomData<fn() -> K>,
+}
+impl<K: AsId, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, value) = self.slots.next()?;
+        Some((K::from_id(id), value))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slots.size_hint()
+    }
+}
+impl<K: AsId, V> ExactSizeIterator for IntoIter<K, V> {}
+impl<K: AsId, V> FusedIterator for IntoIter<K, V> {}
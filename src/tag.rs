@@ -0,0 +1,26 @@
+/// Identifies the arena that handed out an [`Id`](crate::Id).
+///
+/// With the `arena-tags` feature every arena draws a fresh tag and stamps it into
+/// its ids. Without it the tag is zero-sized and every id matches every arena.
+#[cfg(feature = "arena-tags")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct ArenaTag(u32);
+#[cfg(feature = "arena-tags")]
+impl ArenaTag {
+    pub(crate) fn new() -> Self {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[cfg(not(feature = "arena-tags"))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct ArenaTag;
+#[cfg(not(feature = "arena-tags"))]
+impl ArenaTag {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
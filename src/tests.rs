@@ -0,0 +1,832 @@
+use crate::{
+    Arena, ArrayArena, CapacityError, DenseArena, FreeListPolicy, GetManyError, Id, IdSet,
+    InsertWithError, Integer, KeyLayout, LookupError, OverflowPolicy, Packed64, Remappable,
+    SecondaryMap, SparseSecondaryMap, TypedId, U16U16, U32U32, U32U64,
+};
+
+#[test]
+fn raw_views_a_typed_arena() {
+    let mut arena: Arena<String, TypedId<String>> = Arena::new();
+    let a = arena.insert("a".to_owned());
+    let b = arena.insert("b".to_owned());
+    arena.remove(a);
+
+    let raw = arena.raw();
+    assert_eq!(raw.len(), 1);
+    assert_eq!(raw[Id::from(b)], "b");
+    assert!(raw.get(Id::from(a)).is_none());
+
+    let c = Id::from(arena.insert("c".to_owned()));
+    arena.raw_mut()[c].push('!');
+    let d = arena.raw_mut().insert("d".to_owned());
+    assert_eq!(arena[TypedId::from(c)], "c!");
+    assert_eq!(arena[TypedId::from(d)], "d");
+    assert_eq!(arena.len(), 3);
+
+    let raw = arena.into_raw();
+    assert_eq!(raw[d], "d");
+}
+
+#[test]
+fn get_many_mut_disjoint() {
+    let mut arena: Arena<i32> = Arena::new();
+    let a = arena.insert(1);
+    let b = arena.insert(2);
+    let c = arena.insert(3);
+
+    let [x, y, z] = arena.get_many_mut([c, a, b]).unwrap();
+    std::mem::swap(x, y);
+    *z *= 10;
+    assert_eq!([arena[a], arena[b], arena[c]], [3, 20, 1]);
+}
+
+#[test]
+fn get_many_mut_duplicate() {
+    let mut arena: Arena<i32> = Arena::new();
+    let a = arena.insert(1);
+    let b = arena.insert(2);
+
+    assert_eq!(
+        arena.get_many_mut([a, b, a]).unwrap_err(),
+        GetManyError::Duplicate(a)
+    );
+}
+
+#[test]
+fn get_many_mut_stale() {
+    let mut arena: Arena<i32> = Arena::new();
+    let a = arena.insert(1);
+    arena.remove(a);
+    let b = arena.insert(2);
+
+    assert_eq!(
+        arena.get_many_mut([b, a]).unwrap_err(),
+        GetManyError::Missing(
+            a,
+            LookupError::Stale {
+                current_generation: 1
+            }
+        )
+    );
+    assert_eq!(
+        arena
+            .get_many_mut([b, Id::new(5, 0, arena.tag)])
+            .unwrap_err(),
+        GetManyError::Missing(Id::new(5, 0, arena.tag), LookupError::OutOfBounds)
+    );
+}
+
+#[test]
+fn reserve_covers_free_heap_and_quarantine() {
+    let mut arena: Arena<u32> = Arena::with_free_list_policy(FreeListPolicy::LowestIndex);
+    arena.set_quarantine_len(4);
+    arena.reserve(64);
+    let capacities = (
+        arena.slots.capacity(),
+        arena.free_heap.capacity(),
+        arena.quarantine.capacity(),
+    );
+
+    let keys: Vec<_> = (0..64).map(|i| arena.insert(i)).collect();
+    for key in keys {
+        arena.remove(key);
+    }
+    for i in 0..arena.capacity() as u32 {
+        arena.insert(i);
+    }
+    assert_eq!(
+        capacities,
+        (
+            arena.slots.capacity(),
+            arena.free_heap.capacity(),
+            arena.quarantine.capacity(),
+        )
+    );
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn poisoning_reports_where_retain_was_called() {
+    let mut arena: Arena<u32> = Arena::new();
+    arena.set_quarantine_len(4);
+    arena.set_quarantine_poisoning(true);
+    let key = arena.insert(1);
+    arena.retain(|_, _| false);
+
+    let panic = std::panic::catch_unwind(|| arena.get(key)).unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(message.contains(file!()), "{message}");
+}
+
+/// An arena holding `0..n`, together with the keys in index order.
+fn filled(n: u32) -> (Arena<u32>, Vec<Id>) {
+    let mut arena = Arena::new();
+    let keys = (0..n).map(|i| arena.insert(i)).collect();
+    (arena, keys)
+}
+
+#[test]
+fn id_set_insert_after_intersect() {
+    let (_, keys) = filled(100);
+    let mut a: IdSet = [keys[99]].into_iter().collect();
+    let b: IdSet = [keys[0]].into_iter().collect();
+
+    let mut c = &a & &b;
+    assert!(c.is_empty());
+    c.union_with(&a);
+    assert!(c.contains(keys[99]));
+
+    a.intersect_with(&b);
+    assert!(a.is_empty());
+    assert!(a.insert(keys[99]));
+    assert!(a.contains(keys[99]));
+    assert_eq!(a.len(), 1);
+}
+
+#[test]
+fn id_set_operations() {
+    let (mut arena, keys) = filled(200);
+    let a: IdSet = [keys[3], keys[70], keys[150]].into_iter().collect();
+    let b: IdSet = [keys[70], keys[150], keys[199]].into_iter().collect();
+
+    let union = &a | &b;
+    assert_eq!(
+        union.iter().collect::<Vec<_>>(),
+        [keys[3], keys[70], keys[150], keys[199]]
+    );
+    assert_eq!((&a & &b).iter().collect::<Vec<_>>(), [keys[70], keys[150]]);
+    assert_eq!((&a - &b).iter().collect::<Vec<_>>(), [keys[3]]);
+    assert_eq!(union.iter().len(), 4);
+
+    let mut pruned = union.clone();
+    arena.remove(keys[70]);
+    arena.remove(keys[199]);
+    let reused = arena.insert(0);
+    assert_eq!(reused.index(), keys[199].index());
+    pruned.prune(&arena);
+    assert_eq!(pruned.iter().collect::<Vec<_>>(), [keys[3], keys[150]]);
+
+    // A newer generation of a slot replaces the older one, but not the other way round.
+    let mut set = union;
+    assert!(set.insert(reused));
+    assert!(!set.contains(keys[199]));
+    assert!(!set.insert(keys[199]));
+    assert_eq!(set.len(), 4);
+}
+
+#[test]
+fn clear_rejects_every_old_id() {
+    for policy in [
+        FreeListPolicy::Lifo,
+        FreeListPolicy::Fifo,
+        FreeListPolicy::LowestIndex,
+    ] {
+        for quarantine_len in [0, 3] {
+            let mut arena: Arena<u32> = Arena::with_free_list_policy(policy);
+            arena.set_quarantine_len(quarantine_len);
+            let old: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+            arena.remove(old[2]);
+            arena.remove(old[5]);
+            arena.remove(old[7]);
+
+            arena.clear();
+            assert!(arena.is_empty());
+            let new: Vec<_> = (0..20).map(|i| arena.insert(i)).collect();
+            for &key in &old {
+                assert!(!arena.contains(key), "{policy:?} {quarantine_len}: {key}");
+                assert!(arena.get(key).is_none());
+            }
+            assert!(new.iter().all(|&key| arena.contains(key)));
+            assert_eq!(arena.len(), 20);
+        }
+    }
+}
+
+#[test]
+fn reset_reuses_ids() {
+    let (mut arena, old) = filled(5);
+    arena.remove(old[1]);
+    arena.reset();
+    assert!(arena.is_empty());
+
+    let new: Vec<_> = (0..5).map(|i| arena.insert(i + 10)).collect();
+    assert_eq!(new, old);
+    assert_eq!(arena[old[0]], 10);
+}
+
+type SmallArena = Arena<u32, Id<U16U16>>;
+
+/// Removes and reinserts the value at `key` until its slot reaches the last
+/// generation, returning the final key. Needs the slot to be the only free one.
+fn exhaust(arena: &mut SmallArena, mut key: Id<U16U16>) -> Id<U16U16> {
+    let index = key.index();
+    while key.generation() < U16U16::MAX_GENERATION {
+        let value = arena.remove(key).unwrap();
+        key = arena.insert(value);
+        assert_eq!(key.index(), index);
+    }
+    key
+}
+/// Like `exhaust`, but then removes the value for good.
+fn retire(arena: &mut SmallArena, key: Id<U16U16>) {
+    let key = exhaust(arena, key);
+    arena.remove(key);
+}
+
+#[test]
+fn compact_remaps_moved_keys() {
+    let (mut arena, keys) = filled(6);
+    arena.remove(keys[1]);
+    arena.remove(keys[3]);
+    let remap = arena.compact();
+
+    assert_eq!(remap.len(), 4);
+    assert_eq!(remap.get(keys[1]), None);
+    assert_eq!(remap.get(keys[0]), Some(keys[0]));
+    for i in [0, 2, 4, 5] {
+        assert_eq!(arena[remap.get(keys[i]).unwrap()], i as u32);
+    }
+    assert_eq!(arena.iter().copied().collect::<Vec<_>>(), [0, 2, 4, 5]);
+
+    // Old keys of moved values stay rejected, even once their slots are reused.
+    let new: Vec<_> = (10..14).map(|i| arena.insert(i)).collect();
+    for i in [1, 2, 3, 4, 5] {
+        assert!(arena.get(keys[i]).is_none(), "{}", keys[i]);
+    }
+    assert!(new.iter().all(|&key| arena.contains(key)));
+    assert_eq!(arena.len(), 8);
+}
+
+#[test]
+fn compact_remaps_side_tables() {
+    let (mut arena, keys) = filled(5);
+    let mut dense: SecondaryMap<Id, u32> = keys.iter().map(|&key| (key, arena[key])).collect();
+    let mut sparse: SparseSecondaryMap<Id, u32> =
+        keys.iter().map(|&key| (key, arena[key])).collect();
+    let mut set: IdSet = keys.iter().copied().collect();
+
+    arena.remove(keys[0]);
+    arena.remove(keys[2]);
+    let remap = arena.compact();
+    dense.remap(&remap);
+    sparse.remap(&remap);
+    set.remap(&remap);
+
+    assert_eq!(dense.len(), 3);
+    assert_eq!(sparse.len(), 3);
+    assert_eq!(set.len(), 3);
+    for (key, &value) in arena.iter_with_ids() {
+        assert_eq!(dense[key], value);
+        assert_eq!(sparse[key], value);
+        assert!(set.contains(key));
+    }
+}
+
+#[cfg_attr(miri, ignore)]
+#[test]
+fn compact_skips_retired_slots() {
+    let mut arena = SmallArena::new();
+    let keys: Vec<_> = (0..3).map(|i| arena.insert(i)).collect();
+    let last = arena.insert(3);
+    arena.remove(last);
+    retire(&mut arena, keys[1]);
+    arena.remove(keys[0]);
+    assert_eq!(arena.retired_count(), 1);
+
+    let remap = arena.compact();
+    let moved = remap.get(keys[2]).unwrap();
+    assert_eq!(moved.index(), 0);
+    assert_eq!(arena[moved], 2);
+    assert_eq!(arena.retired_count(), 1);
+    assert_eq!(arena.len(), 1);
+
+    let new: Vec<_> = (0..4).map(|i| arena.insert(i)).collect();
+    assert!(new.iter().all(|key| key.index() != 1));
+    assert_eq!(arena.len(), 5);
+    assert_eq!(
+        arena.len() + arena.vacant_count() + arena.retired_count(),
+        arena.slots.len()
+    );
+}
+
+#[test]
+fn compact_releases_quarantine() {
+    let (mut arena, keys) = filled(6);
+    arena.set_quarantine_len(3);
+    arena.remove(keys[1]);
+    arena.remove(keys[4]);
+    assert_eq!(arena.vacant_count(), 0);
+
+    let remap = arena.compact();
+    assert_eq!(arena.len(), 4);
+    let new: Vec<_> = (10..13).map(|i| arena.insert(i)).collect();
+    assert_eq!(arena.len(), 7);
+    for i in [1, 4, 5] {
+        assert!(arena.get(keys[i]).is_none());
+    }
+    for i in [0, 2, 3, 5] {
+        assert_eq!(arena[remap.get(keys[i]).unwrap()], i as u32);
+    }
+    assert!(new.iter().all(|&key| arena.contains(key)));
+}
+
+#[test]
+fn trimmed_ids_stay_stale() {
+    let (mut arena, keys) = filled(4);
+    arena.remove(keys[2]);
+    arena.remove(keys[3]);
+    arena.shrink_to_fit();
+
+    let stale = Err(LookupError::Stale {
+        current_generation: 1,
+    });
+    assert_eq!(arena.try_get(keys[3]), stale);
+    assert!(matches!(
+        arena.entry(keys[3]),
+        crate::entry::Entry::Stale(_)
+    ));
+
+    let new: Vec<_> = (0..2).map(|i| arena.insert(i)).collect();
+    assert_eq!(new[1].index(), keys[3].index());
+    assert_eq!(arena.try_get(keys[3]), stale);
+    assert_eq!(arena.try_get(keys[2]), stale);
+    assert_eq!(arena[new[1]], 1);
+}
+
+#[cfg_attr(miri, ignore)]
+#[test]
+fn index_space_exhaustion() {
+    let mut arena = SmallArena::new();
+    let last = (0..=U16U16::MAX_INDEX as u32)
+        .map(|i| arena.insert(i))
+        .last()
+        .unwrap();
+    assert_eq!(last.index(), U16U16::MAX_INDEX);
+
+    let err = arena.try_insert(7).unwrap_err();
+    assert_eq!(err.kind(), CapacityError::IndexSpaceExhausted);
+    assert_eq!(err.into_value(), 7);
+    assert_eq!(
+        arena.try_insert_with(|_| Ok::<_, ()>(7)),
+        Err(InsertWithError::Full(CapacityError::IndexSpaceExhausted))
+    );
+    assert_eq!(arena.len(), U16U16::MAX_INDEX as usize + 1);
+
+    arena.remove(last);
+    let key = arena.try_insert(7).unwrap();
+    assert_eq!(key.index(), last.index());
+    assert!(std::panic::catch_unwind(move || arena.insert(8)).is_err());
+}
+
+#[test]
+fn max_len() {
+    let mut arena: Arena<u32> = Arena::new();
+    arena.set_max_len(Some(2));
+    let a = arena.insert(1);
+    arena.insert(2);
+
+    assert_eq!(
+        arena.try_insert(3).unwrap_err().kind(),
+        CapacityError::MaxLenReached
+    );
+    let mut called = false;
+    let result = arena.try_insert_with(|_| {
+        called = true;
+        Ok::<_, ()>(3)
+    });
+    assert_eq!(
+        result,
+        Err(InsertWithError::Full(CapacityError::MaxLenReached))
+    );
+    assert!(!called);
+    assert_eq!(
+        arena.try_insert_with(|_| Err::<u32, _>("no")),
+        Err(InsertWithError::Full(CapacityError::MaxLenReached))
+    );
+
+    arena.remove(a);
+    assert_eq!(
+        arena.try_insert_with(|_| Err::<u32, _>("no")),
+        Err(InsertWithError::Failed("no"))
+    );
+    assert_eq!(arena.len(), 1);
+    arena.try_insert(3).unwrap();
+    assert_eq!(arena.len(), 2);
+
+    arena.set_max_len(Some(1));
+    assert_eq!(arena.len(), 2);
+    assert!(arena.try_insert(4).is_err());
+    arena.set_max_len(None);
+    arena.insert(4);
+    assert_eq!(arena.len(), 3);
+}
+
+fn round_trip<L: KeyLayout>() {
+    let tag = crate::ArenaTag::new();
+    for (index, generation) in [
+        (0, L::Generation::ZERO),
+        (L::MAX_INDEX.to_usize(), L::MAX_GENERATION),
+        (L::MAX_INDEX.to_usize(), L::Generation::ZERO),
+        (0, L::MAX_GENERATION),
+    ] {
+        let id = Id::<L>::new(index, generation, tag);
+        assert_eq!(id.index().to_usize(), index, "{id}");
+        assert_eq!(id.generation(), generation, "{id}");
+        assert!(Some(id).is_some());
+    }
+}
+
+#[test]
+fn layouts_round_trip() {
+    round_trip::<U16U16>();
+    round_trip::<U32U32>();
+    round_trip::<U32U64>();
+    round_trip::<Packed64>();
+}
+
+#[cfg_attr(miri, ignore)]
+#[test]
+fn overflow_retires_slot() {
+    let mut arena = SmallArena::new();
+    let first = arena.insert(0);
+    let key = arena.insert(1);
+    retire(&mut arena, key);
+
+    assert_eq!(arena.retired_count(), 1);
+    assert_eq!(arena.len(), 1);
+    assert_eq!(arena.vacant_count(), 0);
+    assert!(arena.capacity() >= arena.len());
+    let new: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+    assert!(new.iter().all(|k| k.index() != key.index()));
+    assert_eq!(arena.len(), 11);
+    assert_eq!(arena[first], 0);
+    assert!(matches!(arena.entry(key), crate::entry::Entry::Stale(e) if e.is_retired()));
+}
+
+#[cfg_attr(miri, ignore)]
+#[test]
+fn overflow_wraps_slot() {
+    let mut arena = SmallArena::new();
+    arena.set_overflow_policy(OverflowPolicy::Wrap);
+    let key = arena.insert(0);
+    let last = exhaust(&mut arena, key);
+    arena.remove(last);
+
+    assert_eq!(arena.retired_count(), 0);
+    let wrapped = arena.insert(1);
+    assert_eq!(wrapped.index(), key.index());
+    assert_eq!(wrapped.generation(), 0);
+    assert!(arena.get(last).is_none());
+    assert_eq!(arena.len(), 1);
+}
+
+#[cfg_attr(miri, ignore)]
+#[test]
+fn clear_at_last_generation() {
+    for policy in [OverflowPolicy::Retire, OverflowPolicy::Wrap] {
+        let mut arena = SmallArena::new();
+        arena.set_overflow_policy(policy);
+        let key = arena.insert(0);
+        let last = exhaust(&mut arena, key);
+        let other = arena.insert(1);
+
+        arena.clear();
+        assert!(arena.is_empty());
+        assert!(!arena.contains(last) && !arena.contains(other));
+        let retired = usize::from(policy == OverflowPolicy::Retire);
+        assert_eq!(arena.retired_count(), retired);
+        assert_eq!(arena.vacant_count(), 2 - retired);
+
+        let new: Vec<_> = (0..2).map(|i| arena.insert(i)).collect();
+        if policy == OverflowPolicy::Retire {
+            assert!(new.iter().all(|k| k.index() != key.index()));
+        }
+        assert!(!arena.contains(last));
+    }
+}
+
+#[test]
+fn array_arena_full_and_reuse() {
+    let mut arena: ArrayArena<&str, 2> = ArrayArena::new();
+    let a = arena.insert("a").unwrap();
+    let b = arena.insert("b").unwrap();
+    assert!(arena.is_full());
+    let err = arena.insert("c").unwrap_err();
+    assert_eq!(err.kind(), CapacityError::MaxLenReached);
+    assert_eq!(err.into_value(), "c");
+
+    assert_eq!(arena.remove(a), Some("a"));
+    let c = arena.insert("c").unwrap();
+    assert_eq!(c.index(), a.index());
+    assert_eq!(c.generation(), a.generation() + 1);
+    assert!(arena.get(a).is_none());
+    assert_eq!(arena[c], "c");
+
+    arena.clear();
+    assert!(arena.is_empty());
+    assert!(!arena.contains(b) && !arena.contains(c));
+    let d = arena.insert("d").unwrap();
+    assert!(d != b && d != c);
+}
+
+#[test]
+fn array_arena_zero_capacity() {
+    let mut arena: ArrayArena<u8, 0> = ArrayArena::new();
+    assert!(arena.is_full());
+    assert_eq!(arena.insert(1).unwrap_err().into_value(), 1);
+    assert_eq!(arena.iter().count(), 0);
+    assert_eq!(arena.drain().count(), 0);
+}
+
+#[test]
+fn array_arena_too_large() {
+    // The debug-build frame for a 65536-slot array does not fit the default test stack.
+    let result = std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(|| ArrayArena::<u8, 65536, Id<U16U16>>::new().len())
+        .unwrap()
+        .join();
+    let message = *result.unwrap_err().downcast::<String>().unwrap();
+    assert!(message.contains("cannot hold"));
+}
+
+#[test]
+fn array_arena_consuming_iterators() {
+    let mut arena: ArrayArena<u32, 4> = ArrayArena::new();
+    let keys: Vec<_> = (0..4).map(|i| arena.insert(i).unwrap()).collect();
+    arena.remove(keys[1]);
+
+    let extracted: Vec<_> = arena.extract_if(|_, v| *v == 2).collect();
+    assert_eq!(extracted, [(keys[2], 2)]);
+    assert_eq!(arena.len(), 2);
+
+    let mut drain = arena.drain();
+    assert_eq!(drain.next(), Some((keys[0], 0)));
+    drop(drain);
+    assert!(arena.is_empty());
+    assert!(!arena.contains(keys[3]));
+
+    let keys: Vec<_> = (0..3).map(|i| arena.insert(i).unwrap()).collect();
+    let mut values: Vec<_> = arena.clone().into_iter().collect();
+    values.sort();
+    assert_eq!(values, [0, 1, 2]);
+    let with_ids: Vec<_> = arena.into_iter_with_ids().rev().collect();
+    assert_eq!(with_ids.len(), 3);
+    assert!(with_ids.iter().all(|&(k, v)| k == keys[v as usize]));
+}
+
+#[test]
+fn entry_kinds() {
+    use crate::entry::Entry;
+
+    let mut arena: Arena<u32> = Arena::new();
+    let a = arena.insert(1);
+    let b = arena.insert(2);
+    arena.remove(b);
+
+    let Entry::Occupied(mut entry) = arena.entry(a) else {
+        panic!("expected an occupied entry");
+    };
+    assert_eq!(entry.replace(10), 1);
+    assert_eq!(arena[a], 10);
+
+    // A stale id into a free slot must not be revived.
+    assert!(matches!(arena.entry(b), Entry::Stale(e) if !e.is_occupied()));
+    // A later generation of the same free slot can be claimed.
+    let ahead = Id::new(b.slot(), b.generation() + 3, arena.tag);
+    let Entry::Vacant(entry) = arena.entry(ahead) else {
+        panic!("expected a vacant entry");
+    };
+    assert!(!entry.is_out_of_bounds());
+    *entry.insert(20) += 1;
+    assert_eq!(arena[ahead], 21);
+    assert!(arena.get(b).is_none());
+    assert!(matches!(arena.entry(b), Entry::Stale(e) if e.is_occupied()));
+    assert_eq!(arena.len(), 2);
+}
+
+#[test]
+fn entry_out_of_bounds_grows() {
+    use crate::entry::Entry;
+
+    let mut arena: Arena<u32> = Arena::new();
+    let a = arena.insert(1);
+    let far = Id::new(4, 0, arena.tag);
+    let Entry::Vacant(entry) = arena.entry(far) else {
+        panic!("expected a vacant entry");
+    };
+    assert!(entry.is_out_of_bounds());
+    assert_eq!(arena.len(), 1);
+
+    assert_eq!(arena.entry(far).or_insert(5), Some(&mut 5));
+    assert_eq!(arena.len(), 2);
+    assert_eq!(arena.vacant_count(), 3);
+    assert_eq!(arena[far], 5);
+    // The slots in between were put on the free list and are reused.
+    let filled: Vec<_> = (0..3).map(|i| arena.insert(i).index()).collect();
+    assert!(filled.iter().all(|&i| (1..4).contains(&i)));
+    assert_eq!(arena[a], 1);
+}
+
+#[cfg_attr(miri, ignore)]
+#[test]
+fn entry_retired_slot() {
+    use crate::entry::Entry;
+
+    let mut arena = SmallArena::new();
+    arena.set_overflow_policy(OverflowPolicy::Retire);
+    let key = arena.insert(0);
+    retire(&mut arena, key);
+
+    for generation in [0, U16U16::MAX_GENERATION] {
+        let id = Id::new(key.slot(), generation, arena.tag);
+        assert!(matches!(arena.entry(id), Entry::Stale(e) if e.is_retired()));
+        assert_eq!(arena.entry(id).or_insert(1), None);
+    }
+    assert!(arena.is_empty());
+}
+
+#[test]
+fn dense_remove_from_middle() {
+    let mut arena: DenseArena<u32> = DenseArena::new();
+    let keys: Vec<_> = (0..5).map(|i| arena.insert(i)).collect();
+
+    assert_eq!(arena.remove(keys[1]), Some(1));
+    assert_eq!(arena.remove(keys[2]), Some(2));
+    assert_eq!(arena.remove(keys[1]), None);
+    assert_eq!(arena.len(), 3);
+
+    for i in [0, 3, 4] {
+        assert_eq!(arena[keys[i]], i as u32);
+    }
+    for (&key, &value) in arena.ids().iter().zip(arena.as_slice()) {
+        assert_eq!(arena[key], value);
+        assert_eq!(key, keys[value as usize]);
+    }
+    assert_eq!(arena.ids().len(), arena.as_slice().len());
+
+    let key = arena.insert(7);
+    assert_eq!(arena.ids().last(), Some(&key));
+    assert_eq!(arena.as_slice().last(), Some(&7));
+}
+
+#[test]
+fn free_list_reuse_order() {
+    for (policy, order) in [
+        (FreeListPolicy::Lifo, [2, 4, 1]),
+        (FreeListPolicy::Fifo, [1, 4, 2]),
+        (FreeListPolicy::LowestIndex, [1, 2, 4]),
+    ] {
+        let mut arena: Arena<u32> = Arena::with_free_list_policy(policy);
+        let keys: Vec<_> = (0..5).map(|i| arena.insert(i)).collect();
+        for i in [1, 4, 2] {
+            arena.remove(keys[i]);
+        }
+        let reused: Vec<_> = (0..3).map(|i| arena.insert(i).slot()).collect();
+        assert_eq!(reused, order, "{policy:?}");
+        assert_eq!(arena.insert(0).slot(), 5, "{policy:?}");
+    }
+}
+
+#[test]
+fn fifo_unlink_last_free() {
+    let mut arena: Arena<u32> = Arena::with_free_list_policy(FreeListPolicy::Fifo);
+    let keys: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+    for i in [1, 3, 5] {
+        arena.remove(keys[i]);
+    }
+
+    // Claiming the tail of the queue through the entry API must move the tail back.
+    let tail = Id::new(5, keys[5].generation() + 1, arena.tag);
+    arena.entry(tail).or_insert(50);
+    arena.remove(keys[0]);
+
+    let reused: Vec<_> = (0..3).map(|i| arena.insert(i).slot()).collect();
+    assert_eq!(reused, [1, 3, 0]);
+    assert_eq!(arena.insert(0).slot(), 6);
+    assert_eq!(arena[tail], 50);
+}
+
+#[test]
+fn drain_dropped_early() {
+    let (mut arena, keys) = filled(5);
+    arena.remove(keys[1]);
+
+    let mut drain = arena.drain();
+    assert_eq!(drain.len(), 4);
+    assert_eq!(drain.next(), Some((keys[0], 0)));
+    drop(drain);
+
+    assert!(arena.is_empty());
+    assert!(keys.iter().all(|&key| arena.get(key).is_none()));
+    let new: Vec<_> = (0..5).map(|i| arena.insert(i)).collect();
+    assert!(new.iter().all(|key| !keys.contains(key)));
+    assert_eq!(arena.len(), 5);
+}
+
+#[test]
+fn extract_if_dropped_early() {
+    let (mut arena, keys) = filled(6);
+
+    {
+        let mut even = arena.extract_if(|_, v| *v % 2 == 0);
+        assert_eq!(even.next(), Some((keys[0], 0)));
+        assert_eq!(even.next(), Some((keys[2], 2)));
+    }
+
+    // Values the iterator never reached stay put, even if they match.
+    assert_eq!(arena.len(), 4);
+    assert!(arena.get(keys[0]).is_none() && arena.get(keys[2]).is_none());
+    for i in [1, 3, 4, 5] {
+        assert_eq!(arena[keys[i]], i as u32);
+    }
+
+    let a = arena.insert(10);
+    let b = arena.insert(12);
+    assert!([a, b].iter().all(|key| [0, 2].contains(&key.slot())));
+    assert!(arena.get(keys[0]).is_none() && arena.get(keys[2]).is_none());
+    assert_eq!(arena.len(), 6);
+}
+
+#[test]
+fn secondary_entry_grows_only_when_filled() {
+    use crate::secondary::Entry;
+
+    let (_, keys) = filled(10);
+    let mut map = SecondaryMap::new();
+
+    assert!(matches!(map.entry(keys[9]), Some(Entry::Vacant(_))));
+    assert_eq!(map.capacity(), 0);
+
+    *map.entry(keys[9]).unwrap().or_insert(0) += 1;
+    assert!(map.capacity() >= 10);
+    assert_eq!(map[keys[9]], 1);
+    assert_eq!(map.len(), 1);
+}
+
+/// A key into a freshly reused slot, together with the stale key it replaced.
+fn reused_key() -> (Id, Id) {
+    let mut arena: Arena<u32> = Arena::new();
+    let old = arena.insert(0);
+    arena.remove(old);
+    let new = arena.insert(1);
+    assert_eq!(new.index(), old.index());
+    (old, new)
+}
+
+#[test]
+fn sparse_secondary_insert_generations() {
+    let (old, new) = reused_key();
+    let mut map = SparseSecondaryMap::new();
+
+    assert_eq!(map.insert(old, "old"), None);
+    // A newer generation replaces the stale value without returning it.
+    assert_eq!(map.insert(new, "new"), None);
+    assert_eq!(map.get(old), None);
+    assert_eq!(map[new], "new");
+    // A stale key is ignored once the slot has moved on.
+    assert_eq!(map.insert(old, "old"), None);
+    assert_eq!(map[new], "new");
+    assert_eq!(map.insert(new, "newer"), Some("new"));
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn sparse_secondary_entry_generations() {
+    use crate::sparse_secondary::Entry;
+
+    let (old, new) = reused_key();
+    let mut map = SparseSecondaryMap::new();
+    map.insert(old, 1);
+
+    assert!(matches!(map.entry(old), Some(Entry::Occupied(_))));
+    assert!(matches!(map.entry(new), Some(Entry::Vacant(_))));
+    *map.entry(new).unwrap().or_insert(10) += 1;
+    assert_eq!(map[new], 11);
+    assert_eq!(map.len(), 1);
+
+    assert!(map.entry(old).is_none());
+    assert!(matches!(map.entry(new), Some(Entry::Occupied(_))));
+}
+
+#[test]
+fn sparse_secondary_retain_sees_latest_keys() {
+    let (old, new) = reused_key();
+    let (_, keys) = filled(3);
+    let mut map = SparseSecondaryMap::new();
+    map.insert(old, 0);
+    map.insert(new, 1);
+    map.insert(old, 2);
+    map.insert(keys[2], 3);
+
+    let mut seen = Vec::new();
+    map.retain(|key, value| {
+        seen.push(key);
+        *value != 3
+    });
+    seen.sort_by_key(|key| key.index());
+    assert_eq!(seen, [new, keys[2]]);
+    assert_eq!(map.len(), 1);
+    assert_eq!(map[new], 1);
+    assert!(!map.contains(old));
+}
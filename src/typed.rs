@@ -0,0 +1,75 @@
+use crate::{GenIndex, Id, KeyLayout, U32U32};
+use std::{
+    fmt::{Debug, Display},
+    hash::Hash,
+    marker::PhantomData,
+};
+
+/// An [`Id`] that only indexes arenas of `T`, without having to declare a newtype.
+///
+/// `TypedId<T>` is `Copy`, `Send` and `Sync` no matter what `T` is.
+pub struct TypedId<T, L: KeyLayout = U32U32> {
+    id: Id<L>,
+    _item: PhantomData<fn() -> T>,
+}
+impl<T, L: KeyLayout> TypedId<T, L> {
+    pub fn new(id: Id<L>) -> Self {
+        Self {
+            id,
+            _item: PhantomData,
+        }
+    }
+    pub fn id(self) -> Id<L> {
+        self.id
+    }
+    pub fn index(self) -> L::Index {
+        self.id.index()
+    }
+    pub fn generation(self) -> L::Generation {
+        self.id.generation()
+    }
+}
+impl<T, L: KeyLayout> GenIndex for TypedId<T, L> {
+    type Item = T;
+    type Layout = L;
+    fn get_id(&self) -> Id<L> {
+        self.id
+    }
+}
+impl<T, L: KeyLayout> From<Id<L>> for TypedId<T, L> {
+    fn from(id: Id<L>) -> Self {
+        Self::new(id)
+    }
+}
+impl<T, L: KeyLayout> From<TypedId<T, L>> for Id<L> {
+    fn from(id: TypedId<T, L>) -> Self {
+        id.id
+    }
+}
+impl<T, L: KeyLayout> Clone for TypedId<T, L> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T, L: KeyLayout> Copy for TypedId<T, L> {}
+impl<T, L: KeyLayout> PartialEq for TypedId<T, L> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+impl<T, L: KeyLayout> Eq for TypedId<T, L> {}
+impl<T, L: KeyLayout> Hash for TypedId<T, L> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+impl<T, L: KeyLayout> Debug for TypedId<T, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TypedId").field(&self.id).finish()
+    }
+}
+impl<T, L: KeyLayout> Display for TypedId<T, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.id, f)
+    }
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "arena-tags")]
+
+use gen_arena::{Arena, ArrayArena, DenseArena, LookupError};
+
+#[test]
+fn foreign_ids_are_rejected() {
+    let mut a: Arena<&str> = Arena::new();
+    let mut b: Arena<&str> = Arena::new();
+    let ka = a.insert("a");
+    let kb = b.insert("b");
+
+    assert_eq!(a.try_get(kb), Err(LookupError::ForeignArena));
+    assert_eq!(b.try_remove(ka), Err(LookupError::ForeignArena));
+}
+
+#[test]
+fn clones_are_separate_arenas() {
+    let mut level: Arena<&str> = Arena::new();
+    let key = level.insert("template");
+    let mut copy = level.clone();
+    let copy_key = copy.iter_with_ids().next().unwrap().0;
+
+    assert_eq!(copy.try_get(key), Err(LookupError::ForeignArena));
+    assert_eq!(level.try_get(copy_key), Err(LookupError::ForeignArena));
+    assert_eq!(copy.remove(copy_key), Some("template"));
+    assert_eq!(level[key], "template");
+
+    let array: ArrayArena<u8, 2> = ArrayArena::new();
+    let mut array_copy = array.clone();
+    let key = array_copy.insert(1).unwrap();
+    assert_eq!(array.try_get(key), Err(LookupError::ForeignArena));
+}
+
+#[test]
+fn cloned_dense_arena_keeps_working() {
+    let mut dense: DenseArena<i32> = DenseArena::new();
+    dense.insert(1);
+    dense.insert(2);
+    let mut copy = dense.clone();
+    let keys: Vec<_> = copy.ids().to_vec();
+
+    assert_eq!(copy.remove(keys[0]), Some(1));
+    assert_eq!(copy[keys[1]], 2);
+    assert!(dense.get(keys[1]).is_none());
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "derive")]
+
+use gen_arena::{Arena, GenIndex, Id, U16U16};
+use std::collections::HashMap;
+
+struct Unit {
+    hp: u32,
+}
+
+#[derive(GenIndex)]
+#[gen_index(item = Unit)]
+struct UnitId(Id);
+
+#[derive(GenIndex)]
+#[gen_index(item = HashMap<u32, u8>)]
+pub struct TableId(pub Id<U16U16>);
+
+type Callback = Box<dyn Fn(u8, u8) -> Vec<u8>>;
+
+#[derive(GenIndex)]
+#[gen_index(item = Box<dyn Fn(u8, u8) -> Vec<u8>>)]
+struct CallbackId(Id);
+
+#[test]
+fn derived_key_indexes_arena() {
+    let mut arena: Arena<Unit, UnitId> = Arena::new();
+    let a = arena.insert(Unit { hp: 10 });
+    let b = arena.insert(Unit { hp: 20 });
+    arena[a].hp += 1;
+
+    assert_eq!(arena[a].hp, 11);
+    assert_eq!(arena.remove(b).map(|unit| unit.hp), Some(20));
+    assert!(arena.get(b).is_none());
+    assert_eq!(Id::from(a), a.get_id());
+    assert_eq!(UnitId::from(a.get_id()), a);
+}
+
+#[test]
+fn derived_key_with_generic_item() {
+    let mut arena: Arena<HashMap<u32, u8>, TableId> = Arena::new();
+    let key = arena.insert(HashMap::from([(1, 2)]));
+    arena[key].insert(3, 4);
+
+    assert_eq!(arena[key].len(), 2);
+    assert_eq!(key.to_string(), "TableId(0, 0)");
+
+    let mut callbacks: Arena<Callback, CallbackId> = Arena::new();
+    let key = callbacks.insert(Box::new(|a, b| vec![a, b]));
+    assert_eq!(callbacks[key](1, 2), [1, 2]);
+}